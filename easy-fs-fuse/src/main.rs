@@ -0,0 +1,72 @@
+use easy_fs::{block_cache_sync_all, EasyFileSystem, RamDisk, BLOCK_SZ};
+use std::env;
+use std::fs::{read_dir, File};
+use std::io::{Read, Result, Write};
+use std::sync::Arc;
+
+/// Size of the virtio-blk disk, 4MiB.
+const IMAGE_BLOCKS: usize = 8192;
+const INODE_BITMAP_BLOCKS: u32 = 1;
+
+fn main() {
+    easy_fs_pack().expect("Error when packing easy-fs!");
+}
+
+fn usage() -> ! {
+    eprintln!("usage: easy-fs-fuse -s <user src bin dir> -t <user target dir>");
+    std::process::exit(1);
+}
+
+fn easy_fs_pack() -> Result<()> {
+    let mut src_path = None;
+    let mut target_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--source" => src_path = args.next(),
+            "-t" | "--target" => target_path = args.next(),
+            _ => usage(),
+        }
+    }
+    let (src_path, target_path) = match (src_path, target_path) {
+        (Some(src_path), Some(target_path)) => (src_path, target_path),
+        _ => usage(),
+    };
+    println!("src_path = {}\ntarget_path = {}", src_path, target_path);
+    // u64 backed so that every block is aligned for the on-disk structures
+    let mut data = vec![0u64; IMAGE_BLOCKS * BLOCK_SZ / 8];
+    let block_device = Arc::new(unsafe { RamDisk::from_raw(data.as_mut_ptr() as *mut u8, IMAGE_BLOCKS) });
+    let efs = EasyFileSystem::create(block_device, IMAGE_BLOCKS as u32, INODE_BITMAP_BLOCKS);
+    let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
+    let mut apps: Vec<_> = read_dir(&src_path)?
+        .map(|dir_entry| {
+            let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
+            name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
+            name_with_ext
+        })
+        .collect();
+    apps.sort();
+    for app in apps {
+        // load app data from host file system
+        let mut host_file = File::open(format!("{}{}", target_path, app))?;
+        let mut all_data: Vec<u8> = Vec::new();
+        host_file.read_to_end(&mut all_data)?;
+        // create a file in easy-fs
+        let inode = root_inode
+            .create(app.as_str())
+            .unwrap_or_else(|| panic!("cannot create {} in easy-fs!", app));
+        // write data to easy-fs
+        inode.write_at(0, all_data.as_slice());
+        println!("app: {} ({} bytes)", app, all_data.len());
+    }
+    drop(root_inode);
+    drop(efs);
+    block_cache_sync_all();
+    // dump the image
+    let bytes = unsafe {
+        std::slice::from_raw_parts(data.as_ptr() as *const u8, IMAGE_BLOCKS * BLOCK_SZ)
+    };
+    let mut f = File::create(format!("{}{}", target_path, "fs.img"))?;
+    f.write_all(bytes)?;
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use super::{BlockDevice, BLOCK_SZ};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::mem::size_of;
+use lazy_static::*;
+use spin::Mutex;
+
+/// Keep the on-disk structures in a cached block properly aligned.
+#[repr(C, align(8))]
+struct CacheData([u8; BLOCK_SZ]);
+
+/// A block in memory, written back to the device when it is dirty and gets evicted or synced.
+pub struct BlockCache {
+    cache: CacheData,
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+    modified: bool,
+}
+
+impl BlockCache {
+    /// Load a new BlockCache from disk.
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = CacheData([0u8; BLOCK_SZ]);
+        block_device.read_block(block_id, &mut cache.0);
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+        }
+    }
+    fn addr_of_offset(&self, offset: usize) -> usize {
+        &self.cache.0[offset] as *const _ as usize
+    }
+    pub fn get_ref<T>(&self, offset: usize) -> &T
+    where
+        T: Sized,
+    {
+        assert!(offset + size_of::<T>() <= BLOCK_SZ);
+        let addr = self.addr_of_offset(offset);
+        unsafe { &*(addr as *const T) }
+    }
+    pub fn get_mut<T>(&mut self, offset: usize) -> &mut T
+    where
+        T: Sized,
+    {
+        assert!(offset + size_of::<T>() <= BLOCK_SZ);
+        self.modified = true;
+        let addr = self.addr_of_offset(offset);
+        unsafe { &mut *(addr as *mut T) }
+    }
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.block_device.write_block(self.block_id, &self.cache.0);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        self.sync()
+    }
+}
+
+const BLOCK_CACHE_SIZE: usize = 16;
+
+/// (device, block_id) of a cached block.
+type CacheKey = (usize, usize);
+
+/// Blocks in use, the least recently used one at the front.
+pub struct BlockCacheManager {
+    queue: VecDeque<(CacheKey, Arc<Mutex<BlockCache>>)>,
+}
+
+/// A device is told apart by its address, which stays the same while a cached block refers to it.
+fn device_id(block_device: &Arc<dyn BlockDevice>) -> usize {
+    Arc::as_ptr(block_device) as *const () as usize
+}
+
+impl BlockCacheManager {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+    pub fn get_block_cache(
+        &mut self,
+        block_id: usize,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Arc<Mutex<BlockCache>> {
+        let key = (device_id(&block_device), block_id);
+        if let Some(idx) = self.queue.iter().position(|pair| pair.0 == key) {
+            // hit: move it to the back as the most recently used one
+            let pair = self.queue.remove(idx).unwrap();
+            let block_cache = Arc::clone(&pair.1);
+            self.queue.push_back(pair);
+            return block_cache;
+        }
+        if self.queue.len() == BLOCK_CACHE_SIZE {
+            // evict the least recently used block which is not in use, it is written back on drop
+            if let Some(idx) = self
+                .queue
+                .iter()
+                .position(|pair| Arc::strong_count(&pair.1) == 1)
+            {
+                self.queue.remove(idx);
+            } else {
+                panic!("Run out of BlockCache!");
+            }
+        }
+        let block_cache = Arc::new(Mutex::new(BlockCache::new(
+            block_id,
+            Arc::clone(&block_device),
+        )));
+        self.queue.push_back((key, Arc::clone(&block_cache)));
+        block_cache
+    }
+}
+
+impl Default for BlockCacheManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> =
+        Mutex::new(BlockCacheManager::new());
+}
+
+pub fn get_block_cache(
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+) -> Arc<Mutex<BlockCache>> {
+    BLOCK_CACHE_MANAGER
+        .lock()
+        .get_block_cache(block_id, block_device)
+}
+
+/// Write all dirty blocks back to their devices.
+///
+/// Locks held by someone else are skipped instead of waited on, so that it is safe
+/// to call it on the way to shutdown, even after a panic inside the filesystem.
+pub fn block_cache_sync_all() {
+    if let Some(manager) = BLOCK_CACHE_MANAGER.try_lock() {
+        for (_, cache) in manager.queue.iter() {
+            if let Some(mut cache) = cache.try_lock() {
+                cache.sync();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RamDisk;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// A disk of zeroed blocks, its memory is leaked so that it outlives the caches.
+    fn ram_disk(blocks: usize) -> Arc<RamDisk> {
+        let data = vec![0u8; blocks * BLOCK_SZ].leak();
+        Arc::new(unsafe { RamDisk::from_raw(data.as_mut_ptr(), blocks) })
+    }
+
+    fn first_byte(disk: &RamDisk, block_id: usize) -> u8 {
+        let mut buf = [0u8; BLOCK_SZ];
+        disk.read_block(block_id, &mut buf);
+        buf[0]
+    }
+
+    fn cached_blocks(manager: &BlockCacheManager) -> Vec<usize> {
+        manager.queue.iter().map(|(key, _)| key.1).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let disk = ram_disk(BLOCK_CACHE_SIZE + 2);
+        let mut manager = BlockCacheManager::new();
+        for block_id in 0..BLOCK_CACHE_SIZE {
+            manager.get_block_cache(block_id, disk.clone());
+        }
+        // block 0 becomes the most recently used one, block 1 the least
+        manager.get_block_cache(0, disk.clone());
+        manager.get_block_cache(BLOCK_CACHE_SIZE, disk.clone());
+        let blocks = cached_blocks(&manager);
+        assert_eq!(blocks.len(), BLOCK_CACHE_SIZE);
+        assert!(!blocks.contains(&1));
+        assert_eq!(blocks[BLOCK_CACHE_SIZE - 2..], [0, BLOCK_CACHE_SIZE]);
+        // a block in use is skipped
+        let in_use = manager.get_block_cache(2, disk.clone());
+        manager.get_block_cache(BLOCK_CACHE_SIZE + 1, disk.clone());
+        let blocks = cached_blocks(&manager);
+        assert!(blocks.contains(&2) && !blocks.contains(&3));
+        drop(in_use);
+    }
+
+    #[test]
+    fn writes_back_dirty_blocks_on_eviction() {
+        let disk = ram_disk(BLOCK_CACHE_SIZE + 1);
+        let mut manager = BlockCacheManager::new();
+        manager
+            .get_block_cache(0, disk.clone())
+            .lock()
+            .modify(0, |byte: &mut u8| *byte = 1);
+        assert_eq!(first_byte(&disk, 0), 0);
+        for block_id in 1..=BLOCK_CACHE_SIZE {
+            manager.get_block_cache(block_id, disk.clone());
+        }
+        assert!(!cached_blocks(&manager).contains(&0));
+        assert_eq!(first_byte(&disk, 0), 1);
+    }
+
+    #[test]
+    fn sync_all_writes_back_dirty_blocks() {
+        let disk = ram_disk(2);
+        get_block_cache(1, disk.clone())
+            .lock()
+            .modify(0, |byte: &mut u8| *byte = 2);
+        assert_eq!(first_byte(&disk, 1), 0);
+        block_cache_sync_all();
+        assert_eq!(first_byte(&disk, 1), 2);
+    }
+
+    #[test]
+    fn keeps_devices_apart() {
+        let (disk_a, disk_b) = (ram_disk(1), ram_disk(1));
+        let mut manager = BlockCacheManager::new();
+        manager
+            .get_block_cache(0, disk_a.clone())
+            .lock()
+            .modify(0, |byte: &mut u8| *byte = 1);
+        let byte = manager
+            .get_block_cache(0, disk_b.clone())
+            .lock()
+            .read(0, |byte: &u8| *byte);
+        assert_eq!(byte, 0);
+    }
+}
@@ -0,0 +1,22 @@
+#![no_std]
+// `div_ceil` is too new for the kernel toolchain
+#![allow(clippy::manual_div_ceil)]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod ram_disk;
+mod vfs;
+
+pub const BLOCK_SZ: usize = 512;
+use bitmap::Bitmap;
+pub use block_cache::{block_cache_sync_all, get_block_cache};
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+use layout::*;
+pub use ram_disk::RamDisk;
+pub use vfs::Inode;
@@ -0,0 +1,49 @@
+use super::{BlockDevice, BLOCK_SZ};
+
+/// A block device which lives in memory, e.g. a buffer on the host or an image loaded by the bootloader.
+pub struct RamDisk {
+    base: usize,
+    blocks: usize,
+}
+
+unsafe impl Send for RamDisk {}
+unsafe impl Sync for RamDisk {}
+
+impl RamDisk {
+    /// # Safety
+    ///
+    /// `[base, base + blocks * BLOCK_SZ)` must stay valid and be used only through
+    /// this disk while it is alive.
+    pub unsafe fn from_raw(base: *mut u8, blocks: usize) -> Self {
+        Self {
+            base: base as usize,
+            blocks,
+        }
+    }
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+    fn block(&self, block_id: usize) -> *mut u8 {
+        assert!(
+            block_id < self.blocks,
+            "block {} out of ram disk!",
+            block_id
+        );
+        (self.base + block_id * BLOCK_SZ) as *mut u8
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        assert_eq!(buf.len(), BLOCK_SZ);
+        unsafe {
+            core::ptr::copy_nonoverlapping(self.block(block_id), buf.as_mut_ptr(), BLOCK_SZ);
+        }
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        assert_eq!(buf.len(), BLOCK_SZ);
+        unsafe {
+            core::ptr::copy_nonoverlapping(buf.as_ptr(), self.block(block_id), BLOCK_SZ);
+        }
+    }
+}
@@ -2,7 +2,11 @@
 
 RUST_TARGET=riscv64gc-unknown-none-elf
 RUSTSBI_QEMU=../../../rcore-os/rustsbi-qemu/target/riscv64imac-unknown-none-elf/release/rustsbi-qemu.bin
+USER_TARGET=../user/target/$RUST_TARGET/release/
+FS_IMG=${USER_TARGET}fs.img
 
-cargo build --target $RUST_TARGET --release \
+(cd ../easy-fs-fuse && cargo run --release -- -s ../user/src/bin/ -t $USER_TARGET) \
+&& cargo build --target $RUST_TARGET --release \
 && rust-objcopy --binary-architecture=riscv64 target/$RUST_TARGET/release/os --strip-all -O binary target/$RUST_TARGET/release/os.bin \
-&& qemu-system-riscv64 -machine virt -nographic -bios $RUSTSBI_QEMU -device loader,file=target/$RUST_TARGET/release/os.bin,addr=0x80200000
+&& qemu-system-riscv64 -machine virt -nographic -bios $RUSTSBI_QEMU -device loader,file=target/$RUST_TARGET/release/os.bin,addr=0x80200000 \
+    -drive file=$FS_IMG,if=none,format=raw,id=x0 -device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0
@@ -1,4 +1,8 @@
 pub const USER_STACK_SIZE: usize = 4096 * 2;
+/// The user stack grows on page faults from `USER_STACK_SIZE` up to this size.
+pub const USER_STACK_LIMIT: usize = 4096 * 256;
+/// mmap without an address places the mapping below this, the top of the lower half of the address space.
+pub const MMAP_TOP: usize = 1 << (VA_WIDTH - 1);
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const CLOCK_FREQ: usize = 12500000;
 pub const TICKS_PER_SEC: usize = 100;
@@ -7,8 +11,31 @@ pub const MAX_APP_LIFETIME_CLOCK: usize = 60 * CLOCK_FREQ; // 1 minute
 pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
 pub const PAGE_SIZE: usize = 4096;
 pub const PAGE_SIZE_BITS: usize = 12;
+/// Levels of the page table, 3 for Sv39, or 4 for Sv48 when built with the `sv48` feature.
+#[cfg(not(feature = "sv48"))]
+pub const PAGE_TABLE_LEVELS: usize = 3;
+#[cfg(feature = "sv48")]
+pub const PAGE_TABLE_LEVELS: usize = 4;
+/// Width of virtual addresses, 39 or 48.
+pub const VA_WIDTH: usize = PAGE_SIZE_BITS + 9 * PAGE_TABLE_LEVELS;
+/// MODE field of satp, 8 for Sv39 and 9 for Sv48.
+#[cfg(not(feature = "sv48"))]
+pub const SATP_MODE: usize = 8;
+#[cfg(feature = "sv48")]
+pub const SATP_MODE: usize = 9;
 pub const MEMORY_END: usize = 0x80800000;
+/// A RAM disk standing in for the swap device, it lives in the memory QEMU gives
+/// beyond what the frame allocator manages.
+pub const SWAP_BASE: usize = MEMORY_END;
+pub const SWAP_SIZE: usize = 0x100_0000;
 
+/// virtio-mmio slot of QEMU's virt machine that the block device is attached to.
+pub const VIRTIO0: usize = 0x10001000;
+pub const MMIO: &[(usize, usize)] = &[
+    (VIRTIO0, 0x1000),
+];
+
+/// The highest page, which is canonical in Sv39 and Sv48 alike.
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
@@ -0,0 +1,33 @@
+mod virtio_blk;
+
+pub use virtio_blk::VirtIOBlock;
+
+use alloc::sync::Arc;
+use lazy_static::*;
+use crate::config::VIRTIO0;
+
+lazy_static! {
+    pub static ref BLOCK_DEVICE: Arc<VirtIOBlock> = Arc::new(VirtIOBlock::new(VIRTIO0));
+}
+
+/// Write patterns to a few sectors and read them back, the original data is restored.
+/// It overwrites the superblock for a while, so only boot it with a scratch image.
+#[cfg(feature = "block-test")]
+pub fn block_device_test() {
+    use easy_fs::BlockDevice;
+    let block_device = BLOCK_DEVICE.clone();
+    let mut origin = [0u8; 512];
+    let mut write_buffer = [0u8; 512];
+    let mut read_buffer = [0u8; 512];
+    for i in 0..8 {
+        block_device.read_block(i, &mut origin);
+        for byte in write_buffer.iter_mut() {
+            *byte = i as u8;
+        }
+        block_device.write_block(i, &write_buffer);
+        block_device.read_block(i, &mut read_buffer);
+        assert_eq!(write_buffer, read_buffer);
+        block_device.write_block(i, &origin);
+    }
+    println!("block device test passed!");
+}
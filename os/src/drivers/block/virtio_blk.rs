@@ -0,0 +1,332 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::sync::atomic::{fence, Ordering};
+use easy_fs::BlockDevice;
+use crate::config::PAGE_SIZE;
+use crate::mm::address::PhysAddr;
+use crate::mm::frame_allocator::{frame_alloc_contiguous, FrameTracker};
+use crate::sync::UPSafeCell;
+
+/// Size of a sector, the unit of virtio-blk requests.
+const SECTOR_SIZE: usize = 512;
+/// Only one request is in flight at a time, a tiny queue is enough.
+const QUEUE_SIZE: usize = 8;
+/// Used ring alignment for legacy devices, small enough to keep the whole queue in one frame.
+const QUEUE_ALIGN: usize = 64;
+
+const VIRTIO_MAGIC: u32 = 0x7472_6976;
+const VIRTIO_DEVICE_BLOCK: u32 = 2;
+/// Feature bit 32, i.e. bit 0 of the second feature word.
+const VIRTIO_F_VERSION_1: u32 = 1 << 0;
+
+// virtio-mmio registers
+const MMIO_MAGIC_VALUE: usize = 0x000;
+const MMIO_VERSION: usize = 0x004;
+const MMIO_DEVICE_ID: usize = 0x008;
+const MMIO_DEVICE_FEATURES: usize = 0x010;
+const MMIO_DEVICE_FEATURES_SEL: usize = 0x014;
+const MMIO_DRIVER_FEATURES: usize = 0x020;
+const MMIO_DRIVER_FEATURES_SEL: usize = 0x024;
+const MMIO_GUEST_PAGE_SIZE: usize = 0x028;
+const MMIO_QUEUE_SEL: usize = 0x030;
+const MMIO_QUEUE_NUM_MAX: usize = 0x034;
+const MMIO_QUEUE_NUM: usize = 0x038;
+const MMIO_QUEUE_ALIGN: usize = 0x03c;
+const MMIO_QUEUE_PFN: usize = 0x040;
+const MMIO_QUEUE_READY: usize = 0x044;
+const MMIO_QUEUE_NOTIFY: usize = 0x050;
+const MMIO_INTERRUPT_STATUS: usize = 0x060;
+const MMIO_INTERRUPT_ACK: usize = 0x064;
+const MMIO_STATUS: usize = 0x070;
+const MMIO_QUEUE_DESC_LOW: usize = 0x080;
+const MMIO_QUEUE_DESC_HIGH: usize = 0x084;
+const MMIO_QUEUE_DRIVER_LOW: usize = 0x090;
+const MMIO_QUEUE_DRIVER_HIGH: usize = 0x094;
+const MMIO_QUEUE_DEVICE_LOW: usize = 0x0a0;
+const MMIO_QUEUE_DEVICE_HIGH: usize = 0x0a4;
+const MMIO_CONFIG_CAPACITY: usize = 0x100;
+
+bitflags! {
+    struct DeviceStatus: u32 {
+        const ACKNOWLEDGE = 1;
+        const DRIVER = 2;
+        const DRIVER_OK = 4;
+        const FEATURES_OK = 8;
+        const FAILED = 128;
+    }
+}
+
+bitflags! {
+    struct DescFlags: u16 {
+        const NEXT = 1;
+        const WRITE = 2;
+    }
+}
+
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_S_OK: u8 = 0;
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct AvailRing {
+    flags: u16,
+    idx: u16,
+    ring: [u16; QUEUE_SIZE],
+    used_event: u16,
+}
+
+#[repr(C)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct UsedRing {
+    flags: u16,
+    idx: u16,
+    ring: [UsedElem; QUEUE_SIZE],
+    avail_event: u16,
+}
+
+#[repr(C)]
+struct BlkReqHeader {
+    type_: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+// layout of the queue frame: descriptor table, available ring, used ring
+const DESC_OFFSET: usize = 0;
+const AVAIL_OFFSET: usize = DESC_OFFSET + size_of::<Descriptor>() * QUEUE_SIZE;
+const USED_OFFSET: usize =
+    (AVAIL_OFFSET + size_of::<AvailRing>() + QUEUE_ALIGN - 1) / QUEUE_ALIGN * QUEUE_ALIGN;
+// layout of the request frame: header, status, and the bounced sector data
+const HEADER_OFFSET: usize = 0;
+const STATUS_OFFSET: usize = size_of::<BlkReqHeader>();
+const DATA_OFFSET: usize = SECTOR_SIZE;
+const QUEUE_PAGES: usize = (USED_OFFSET + size_of::<UsedRing>() + PAGE_SIZE - 1) / PAGE_SIZE;
+const REQ_PAGES: usize = (DATA_OFFSET + SECTOR_SIZE + PAGE_SIZE - 1) / PAGE_SIZE;
+
+/// Physically contiguous frames shared with the device, the whole run is freed on drop.
+struct DmaBuffer {
+    frames: Vec<FrameTracker>,
+}
+
+impl DmaBuffer {
+    fn new(pages: usize) -> Self {
+        Self {
+            frames: frame_alloc_contiguous(pages, 1).expect("no memory for virtio-blk DMA!"),
+        }
+    }
+    fn pa(&self, offset: usize) -> usize {
+        let pa: PhysAddr = self.frames[0].ppn.into();
+        pa.0 + offset
+    }
+    fn bytes(&self, offset: usize, len: usize) -> &'static mut [u8] {
+        assert!(offset + len <= self.frames.len() * PAGE_SIZE);
+        unsafe { core::slice::from_raw_parts_mut(self.pa(offset) as *mut u8, len) }
+    }
+}
+
+pub struct VirtIOBlock {
+    inner: UPSafeCell<VirtIOBlockInner>,
+}
+
+struct VirtIOBlockInner {
+    base: usize,
+    capacity: usize,
+    /// Descriptor table, available ring and used ring.
+    queue: DmaBuffer,
+    /// DMA buffer of a request: the callers' buffers may live on kernel stacks,
+    /// which are not identically mapped, so data is bounced through here.
+    req: DmaBuffer,
+    last_used_idx: u16,
+}
+
+impl VirtIOBlock {
+    pub fn new(base: usize) -> Self {
+        Self {
+            inner: unsafe { UPSafeCell::new(VirtIOBlockInner::new(base)) },
+        }
+    }
+    /// Number of sectors on the device.
+    #[allow(unused)]
+    pub fn capacity(&self) -> usize {
+        self.inner.exclusive_access().capacity
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        assert_eq!(buf.len(), SECTOR_SIZE);
+        let mut inner = self.inner.exclusive_access();
+        inner.request(VIRTIO_BLK_T_IN, block_id);
+        buf.copy_from_slice(inner.req.bytes(DATA_OFFSET, SECTOR_SIZE));
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        assert_eq!(buf.len(), SECTOR_SIZE);
+        let mut inner = self.inner.exclusive_access();
+        inner.req.bytes(DATA_OFFSET, SECTOR_SIZE).copy_from_slice(buf);
+        inner.request(VIRTIO_BLK_T_OUT, block_id);
+    }
+}
+
+impl VirtIOBlockInner {
+    fn new(base: usize) -> Self {
+        let mut inner = Self {
+            base,
+            capacity: 0,
+            queue: DmaBuffer::new(QUEUE_PAGES),
+            req: DmaBuffer::new(REQ_PAGES),
+            last_used_idx: 0,
+        };
+        inner.init();
+        inner
+    }
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { ((self.base + offset) as *const u32).read_volatile() }
+    }
+    fn write_reg(&self, offset: usize, value: u32) {
+        unsafe { ((self.base + offset) as *mut u32).write_volatile(value) }
+    }
+    fn set_status(&self, status: DeviceStatus) {
+        self.write_reg(MMIO_STATUS, status.bits);
+    }
+    fn queue_pa(&self, offset: usize) -> usize {
+        self.queue.pa(offset)
+    }
+    fn req_pa(&self, offset: usize) -> usize {
+        self.req.pa(offset)
+    }
+    fn init(&mut self) {
+        assert_eq!(self.read_reg(MMIO_MAGIC_VALUE), VIRTIO_MAGIC, "virtio-mmio not found at {:#x}!", self.base);
+        assert_eq!(self.read_reg(MMIO_DEVICE_ID), VIRTIO_DEVICE_BLOCK, "virtio device at {:#x} is not a block device!", self.base);
+        let version = self.read_reg(MMIO_VERSION);
+        let legacy = version == 1;
+        // reset and say hello
+        self.set_status(DeviceStatus::empty());
+        let mut status = DeviceStatus::ACKNOWLEDGE;
+        self.set_status(status);
+        status |= DeviceStatus::DRIVER;
+        self.set_status(status);
+        // negotiate features: we use none except VIRTIO_F_VERSION_1 on modern devices
+        self.write_reg(MMIO_DRIVER_FEATURES_SEL, 0);
+        self.write_reg(MMIO_DRIVER_FEATURES, 0);
+        if !legacy {
+            self.write_reg(MMIO_DEVICE_FEATURES_SEL, 1);
+            assert!(self.read_reg(MMIO_DEVICE_FEATURES) & VIRTIO_F_VERSION_1 != 0);
+            self.write_reg(MMIO_DRIVER_FEATURES_SEL, 1);
+            self.write_reg(MMIO_DRIVER_FEATURES, VIRTIO_F_VERSION_1);
+            status |= DeviceStatus::FEATURES_OK;
+            self.set_status(status);
+            assert!(
+                self.read_reg(MMIO_STATUS) & DeviceStatus::FEATURES_OK.bits != 0,
+                "virtio-blk rejected our features!"
+            );
+        } else {
+            self.write_reg(MMIO_GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+        }
+        // set up queue 0
+        self.write_reg(MMIO_QUEUE_SEL, 0);
+        assert!(self.read_reg(MMIO_QUEUE_NUM_MAX) as usize >= QUEUE_SIZE, "virtio-blk queue is too small!");
+        self.write_reg(MMIO_QUEUE_NUM, QUEUE_SIZE as u32);
+        if legacy {
+            self.write_reg(MMIO_QUEUE_ALIGN, QUEUE_ALIGN as u32);
+            self.write_reg(MMIO_QUEUE_PFN, (self.queue_pa(0) / PAGE_SIZE) as u32);
+        } else {
+            let desc = self.queue_pa(DESC_OFFSET);
+            let avail = self.queue_pa(AVAIL_OFFSET);
+            let used = self.queue_pa(USED_OFFSET);
+            self.write_reg(MMIO_QUEUE_DESC_LOW, desc as u32);
+            self.write_reg(MMIO_QUEUE_DESC_HIGH, (desc >> 32) as u32);
+            self.write_reg(MMIO_QUEUE_DRIVER_LOW, avail as u32);
+            self.write_reg(MMIO_QUEUE_DRIVER_HIGH, (avail >> 32) as u32);
+            self.write_reg(MMIO_QUEUE_DEVICE_LOW, used as u32);
+            self.write_reg(MMIO_QUEUE_DEVICE_HIGH, (used >> 32) as u32);
+            self.write_reg(MMIO_QUEUE_READY, 1);
+        }
+        status |= DeviceStatus::DRIVER_OK;
+        self.set_status(status);
+        assert!(self.read_reg(MMIO_STATUS) & DeviceStatus::FAILED.bits == 0, "virtio-blk init failed!");
+        self.capacity = self.read_reg(MMIO_CONFIG_CAPACITY) as usize
+            | (self.read_reg(MMIO_CONFIG_CAPACITY + 4) as usize) << 32;
+        info!(
+            "virtio-blk at {:#x}: version={}, capacity={} sectors",
+            self.base, version, self.capacity
+        );
+    }
+    fn desc_table(&self) -> &'static mut [Descriptor] {
+        let ptr = self.queue_pa(DESC_OFFSET) as *mut Descriptor;
+        unsafe { core::slice::from_raw_parts_mut(ptr, QUEUE_SIZE) }
+    }
+    fn avail_ring(&self) -> &'static mut AvailRing {
+        PhysAddr::from(self.queue_pa(AVAIL_OFFSET)).get_mut()
+    }
+    fn used_ring(&self) -> &'static mut UsedRing {
+        PhysAddr::from(self.queue_pa(USED_OFFSET)).get_mut()
+    }
+    /// Issue a request built from 3 descriptors: header, data and status, then poll for it.
+    fn request(&mut self, type_: u32, block_id: usize) {
+        assert!(block_id < self.capacity, "sector {} out of virtio-blk!", block_id);
+        *PhysAddr::from(self.req_pa(HEADER_OFFSET)).get_mut() = BlkReqHeader {
+            type_,
+            reserved: 0,
+            sector: block_id as u64,
+        };
+        let status: &mut u8 = PhysAddr::from(self.req_pa(STATUS_OFFSET)).get_mut();
+        *status = 0xff;
+        let data_flags = if type_ == VIRTIO_BLK_T_IN {
+            DescFlags::NEXT | DescFlags::WRITE
+        } else {
+            DescFlags::NEXT
+        };
+        let desc = self.desc_table();
+        desc[0] = Descriptor {
+            addr: self.req_pa(HEADER_OFFSET) as u64,
+            len: size_of::<BlkReqHeader>() as u32,
+            flags: DescFlags::NEXT.bits,
+            next: 1,
+        };
+        desc[1] = Descriptor {
+            addr: self.req_pa(DATA_OFFSET) as u64,
+            len: SECTOR_SIZE as u32,
+            flags: data_flags.bits,
+            next: 2,
+        };
+        desc[2] = Descriptor {
+            addr: self.req_pa(STATUS_OFFSET) as u64,
+            len: 1,
+            flags: DescFlags::WRITE.bits,
+            next: 0,
+        };
+        let avail = self.avail_ring();
+        let avail_idx = unsafe { (&avail.idx as *const u16).read_volatile() };
+        avail.ring[avail_idx as usize % QUEUE_SIZE] = 0;
+        // the descriptors must be visible before the index is
+        fence(Ordering::SeqCst);
+        unsafe { (&mut avail.idx as *mut u16).write_volatile(avail_idx.wrapping_add(1)) };
+        fence(Ordering::SeqCst);
+        self.write_reg(MMIO_QUEUE_NOTIFY, 0);
+        // poll until the device hands the chain back
+        let used = self.used_ring();
+        while unsafe { (&used.idx as *const u16).read_volatile() } == self.last_used_idx {
+            core::hint::spin_loop();
+        }
+        fence(Ordering::SeqCst);
+        self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        // we poll, so just acknowledge whatever interrupt is pending
+        let interrupt_status = self.read_reg(MMIO_INTERRUPT_STATUS);
+        self.write_reg(MMIO_INTERRUPT_ACK, interrupt_status);
+        let status = unsafe { (status as *const u8).read_volatile() };
+        assert_eq!(status, VIRTIO_BLK_S_OK, "virtio-blk request on sector {} failed!", block_id);
+    }
+}
@@ -0,0 +1,25 @@
+mod inode;
+mod pipe;
+mod stdio;
+
+use crate::mm::UserBuffer;
+use alloc::sync::Arc;
+use easy_fs::Inode;
+
+/// Anything a file descriptor can refer to.
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    /// Read into `buf`, return the number of bytes read.
+    fn read(&self, buf: UserBuffer) -> usize;
+    /// Write from `buf`, return the number of bytes written.
+    fn write(&self, buf: UserBuffer) -> usize;
+    /// The easy-fs inode behind the file, None if it can not be mapped.
+    fn inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
+}
+
+pub use inode::{list_apps, open_file, read_app, OpenFlags};
+pub use pipe::make_pipe;
+pub use stdio::{Stdin, Stdout};
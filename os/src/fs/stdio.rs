@@ -0,0 +1,61 @@
+use crate::mm::UserBuffer;
+use crate::sbi::{console_getchar, console_putchar};
+use crate::task::suspend_current_and_run_next;
+use super::File;
+
+pub struct Stdin;
+
+pub struct Stdout;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    /// Block until a byte arrives, then take what else is pending, up to the buffer length.
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        let mut count = 0;
+        for byte in user_buf {
+            let mut c = console_getchar();
+            while c == 0 || c == usize::MAX {
+                if count > 0 {
+                    return count;
+                }
+                // no input yet, let other tasks run
+                suspend_current_and_run_next();
+                c = console_getchar();
+            }
+            unsafe {
+                *byte = c as u8;
+            }
+            count += 1;
+        }
+        count
+    }
+    fn write(&self, _user_buf: UserBuffer) -> usize {
+        panic!("Cannot write to stdin!");
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, _user_buf: UserBuffer) -> usize {
+        panic!("Cannot read from stdout!");
+    }
+    fn write(&self, user_buf: UserBuffer) -> usize {
+        // byte by byte, user data need not be UTF-8 and a char may be split across pages
+        for buffer in user_buf.buffers.iter() {
+            for byte in buffer.iter() {
+                console_putchar(*byte as usize);
+            }
+        }
+        user_buf.len()
+    }
+}
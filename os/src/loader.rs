@@ -1,38 +0,0 @@
-pub fn get_num_app() -> usize {
-    extern "C" {
-        fn _num_app();
-    }
-    unsafe { (_num_app as usize as *const usize).read_volatile() }
-}
-
-pub fn get_app_data(app_id: usize) -> &'static [u8] {
-    extern "C" {
-        fn _num_app();
-    }
-    let num_app_ptr = _num_app as usize as *const usize;
-    let num_app = get_num_app();
-    let app_start = unsafe { core::slice::from_raw_parts(num_app_ptr.add(1), num_app + 1) };
-    assert!(app_id < num_app);
-    unsafe {
-        core::slice::from_raw_parts(
-            app_start[app_id] as *const u8,
-            app_start[app_id + 1] - app_start[app_id],
-        )
-    }
-}
-
-pub fn get_app_name(app_id: usize) -> &'static str {
-    extern "C" {
-        fn _app_names();
-    }
-    let num_app = get_num_app();
-    assert!(app_id < num_app);
-    let app_names_ptr = _app_names as usize as *const usize;
-    unsafe {
-        let app_names_raw: &[usize] = core::slice::from_raw_parts(app_names_ptr, num_app + 1);
-        core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                app_names_raw[app_id] as *const u8,
-                app_names_raw[app_id + 1] - app_names_raw[app_id],
-        ))
-    }
-}
\ No newline at end of file
@@ -16,8 +16,9 @@ mod console;
 #[macro_use]
 mod log;
 mod config;
+mod drivers;
+mod fs;
 mod lang_items;
-mod loader;
 mod sbi;
 mod syscall;
 mod task;
@@ -27,7 +28,6 @@ mod mm;
 mod sync;
 
 global_asm!(include_str!("entry.asm"));
-global_asm!(include_str!("link_app.S"));
 
 fn clear_bss() {
     extern "C" {
@@ -43,8 +43,12 @@ pub fn rust_main() {
     println!("Hello, world!");
     mm::init();
     mm::memory_set::remap_test();
+    #[cfg(feature = "block-test")]
+    drivers::block::block_device_test();
     trap::init();
     trap::enable_timer_interrupt();
     timer::set_next_trigger();
-    task::run_first_task();
+    fs::list_apps();
+    task::add_initproc();
+    task::run_tasks();
 }
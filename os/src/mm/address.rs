@@ -1,11 +1,10 @@
 use core::fmt::{self, Debug, Formatter};
-use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS};
+use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS, PAGE_TABLE_LEVELS, VA_WIDTH};
 use crate::mm::page_table::PageTableEntry;
 
-const PA_WIDTH_SV39: usize = 56;
-const VA_WIDTH_SV39: usize = 39;
-const PPN_WIDTH_SV39: usize = PA_WIDTH_SV39 - PAGE_SIZE_BITS;
-const VPN_WIDTH_SV39: usize = VA_WIDTH_SV39 - PAGE_SIZE_BITS;
+const PA_WIDTH: usize = 56;
+const PPN_WIDTH: usize = PA_WIDTH - PAGE_SIZE_BITS;
+const VPN_WIDTH: usize = VA_WIDTH - PAGE_SIZE_BITS;
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct PhysAddr(pub usize);
@@ -54,13 +53,13 @@ impl VirtAddr {
 
 impl From<usize> for VirtAddr {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << VA_WIDTH_SV39) - 1))
+        Self(v & ((1 << VA_WIDTH) - 1))
     }
 }
 
 impl From<usize> for VirtPageNum {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << VPN_WIDTH_SV39) - 1))
+        Self(v & ((1 << VPN_WIDTH) - 1))
     }
 }
 
@@ -84,10 +83,11 @@ impl From<VirtAddr> for usize {
 }
 
 impl VirtPageNum {
-    pub fn indexes(&self) -> [usize; 3] {
+    /// Index into the page table of each level, from the root.
+    pub fn indexes(&self) -> [usize; PAGE_TABLE_LEVELS] {
         let mut vpn = self.0;
-        let mut idx = [0usize; 3];
-        for i in (0..3).rev() {
+        let mut idx = [0usize; PAGE_TABLE_LEVELS];
+        for i in (0..PAGE_TABLE_LEVELS).rev() {
             idx[i] = vpn & 511;
             vpn >>= 9;
         }
@@ -97,7 +97,7 @@ impl VirtPageNum {
 
 impl From<usize> for PhysAddr {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << PA_WIDTH_SV39) - 1))
+        Self(v & ((1 << PA_WIDTH) - 1))
     }
 }
 
@@ -123,6 +123,9 @@ impl PhysAddr {
     pub fn ceil(&self) -> PhysPageNum {
         PhysPageNum((self.0 + PAGE_SIZE - 1) / PAGE_SIZE)
     }
+    pub fn get_mut<T>(&self) -> &'static mut T {
+        unsafe { (self.0 as *mut T).as_mut().unwrap() }
+    }
 }
 
 impl PhysPageNum {
@@ -161,7 +164,7 @@ impl From<PhysPageNum> for PhysAddr {
 
 impl From<usize> for PhysPageNum {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << PPN_WIDTH_SV39) - 1))
+        Self(v & ((1 << PPN_WIDTH) - 1))
     }
 }
 
@@ -0,0 +1,93 @@
+use crate::sync::UPSafeCell;
+use crate::task::{record_page_event, PageEvent};
+use lazy_static::*;
+use riscv::register::satp;
+
+/// Position of the ASID field in satp.
+pub const ASID_SHIFT: usize = 44;
+const ASID_MASK: usize = 0xffff;
+
+/// Address space identifier of a user space, it is valid as long as its generation
+/// is the current one.
+#[derive(Copy, Clone)]
+pub struct Asid {
+    generation: usize,
+    id: usize,
+}
+
+impl Asid {
+    /// An ASID that is not assigned yet.
+    pub fn new() -> Self {
+        Self { generation: 0, id: 0 }
+    }
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+/// Hands out ASIDs in generations, ASID 0 belongs to the kernel space.
+///
+/// When a generation runs out, the whole TLB is flushed and every user space
+/// gets a new ASID the next time it is switched to.
+pub struct AsidAllocator {
+    generation: usize,
+    next: usize,
+    /// the largest ASID kept by the hardware, 0 if there are none
+    max: usize,
+}
+
+impl AsidAllocator {
+    pub fn new() -> Self {
+        Self {
+            generation: 1,
+            next: 1,
+            max: 0,
+        }
+    }
+    /// Find out how many ASID bits the hardware implements, the unimplemented ones
+    /// read back as 0. Paging must be on.
+    pub fn init(&mut self) {
+        let token = satp::read().bits();
+        unsafe {
+            satp::write(token | ASID_MASK << ASID_SHIFT);
+            self.max = (satp::read().bits() >> ASID_SHIFT) & ASID_MASK;
+            satp::write(token);
+        }
+    }
+    pub fn enabled(&self) -> bool {
+        self.max > 0
+    }
+    /// Assign a new ASID unless `asid` belongs to the current generation.
+    pub fn refresh(&mut self, asid: &mut Asid) {
+        if !self.enabled() || asid.generation == self.generation {
+            return;
+        }
+        if self.next > self.max {
+            // the TLB may still hold entries of the ASIDs handed out before
+            self.generation += 1;
+            self.next = 1;
+            unsafe {
+                asm!("sfence.vma");
+            }
+            record_page_event(PageEvent::FullTlbFlush);
+        }
+        asid.generation = self.generation;
+        asid.id = self.next;
+        self.next += 1;
+    }
+}
+
+lazy_static! {
+    pub static ref ASID_ALLOCATOR: UPSafeCell<AsidAllocator> =
+        unsafe { UPSafeCell::new(AsidAllocator::new()) };
+}
+
+pub fn init_asid_allocator() {
+    ASID_ALLOCATOR.exclusive_access().init();
+}
+
+/// Whether the TLB entries are tagged by ASIDs, otherwise it must be flushed
+/// on every switch between address spaces.
+pub fn asid_enabled() -> bool {
+    ASID_ALLOCATOR.exclusive_access().enabled()
+}
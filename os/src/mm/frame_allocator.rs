@@ -1,59 +1,132 @@
 use crate::config::MEMORY_END;
 use crate::mm::address::{PhysAddr, PhysPageNum};
+use crate::mm::swap::swap_out_one;
 use crate::sync::UPSafeCell;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
 
 trait FrameAllocator {
     fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
+    /// Allocate `count` physically contiguous frames, the first one aligned to `align` frames.
+    fn alloc_contiguous(&mut self, count: usize, align: usize) -> Option<PhysPageNum>;
+    /// Drop a reference, the frame is recycled once nobody refers to it.
     fn dealloc(&mut self, ppn: PhysPageNum);
+    fn add_ref(&mut self, ppn: PhysPageNum);
+    fn ref_count(&self, ppn: PhysPageNum) -> usize;
 }
 
-pub struct StackFrameAllocator {
-    current: usize, //空闲内存的起始物理页号
-    end: usize,     //空闲内存的结束物理页号
-    recycled: Vec<usize>,
+/// Blocks of up to 2^(MAX_ORDER - 1) frames.
+const MAX_ORDER: usize = 20;
+
+/// Buddy system allocator, a block of 2^order frames starts at a ppn aligned to its size
+/// and is merged with its buddy when both are free.
+pub struct BuddyFrameAllocator {
+    /// start ppns of the free blocks of each order
+    free_lists: Vec<BTreeSet<usize>>,
+    base: usize,    //可分配的第一个物理页号
+    end: usize,     //可分配内存的结束物理页号
+    ref_counts: Vec<u32>,
 }
 
-impl FrameAllocator for StackFrameAllocator {
+impl FrameAllocator for BuddyFrameAllocator {
     fn new() -> Self {
         Self {
-            current: 0,
+            free_lists: Vec::new(),
+            base: 0,
             end: 0,
-            recycled: Vec::new(),
+            ref_counts: Vec::new(),
         }
     }
     fn alloc(&mut self) -> Option<PhysPageNum> {
-        if let Some(ppn) = self.recycled.pop() {
-            Some(ppn.into())
-        } else {
-            if self.current == self.end {
-                None
-            } else {
-                self.current += 1;
-                Some((self.current - 1).into())
-            }
+        self.alloc_contiguous(1, 1)
+    }
+    fn alloc_contiguous(&mut self, count: usize, align: usize) -> Option<PhysPageNum> {
+        assert!(count > 0 && align.is_power_of_two());
+        let order = order_of(count.max(align));
+        let ppn = self.alloc_block(order)?;
+        // hand the frames beyond the run back
+        for free in ppn + count..ppn + (1 << order) {
+            self.free_block(free, 0);
         }
+        for ref_count in &mut self.ref_counts[ppn - self.base..ppn - self.base + count] {
+            *ref_count = 1;
+        }
+        Some(ppn.into())
     }
     fn dealloc(&mut self, ppn: PhysPageNum) {
         let ppn = ppn.0;
         // validity check
-        if ppn >= self.current || self.recycled.iter().find(|&v| *v == ppn).is_some() {
+        if ppn < self.base || ppn >= self.end || self.ref_counts[ppn - self.base] == 0 {
             panic!("Frame ppn={:#x} has not been allocated!", ppn);
         }
+        self.ref_counts[ppn - self.base] -= 1;
         // recycle
-        self.recycled.push(ppn);
+        if self.ref_counts[ppn - self.base] == 0 {
+            self.free_block(ppn, 0);
+        }
+    }
+    fn add_ref(&mut self, ppn: PhysPageNum) {
+        let ppn = ppn.0;
+        if ppn < self.base || ppn >= self.end || self.ref_counts[ppn - self.base] == 0 {
+            panic!("Frame ppn={:#x} has not been allocated!", ppn);
+        }
+        self.ref_counts[ppn - self.base] += 1;
+    }
+    fn ref_count(&self, ppn: PhysPageNum) -> usize {
+        self.ref_counts[ppn.0 - self.base] as usize
     }
 }
 
-impl StackFrameAllocator {
+impl BuddyFrameAllocator {
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
-        self.current = l.0;
+        self.base = l.0;
         self.end = r.0;
+        self.ref_counts = vec![0; r.0 - l.0];
+        self.free_lists = (0..MAX_ORDER).map(|_| BTreeSet::new()).collect();
+        // cut [l, r) into the largest aligned blocks
+        let mut ppn = l.0;
+        while ppn < r.0 {
+            let mut order = (ppn.trailing_zeros() as usize).min(MAX_ORDER - 1);
+            while ppn + (1 << order) > r.0 {
+                order -= 1;
+            }
+            self.free_lists[order].insert(ppn);
+            ppn += 1 << order;
+        }
+    }
+    /// Take a free block of 2^order frames, splitting a larger one if needed.
+    fn alloc_block(&mut self, order: usize) -> Option<usize> {
+        let from = (order..MAX_ORDER).find(|o| !self.free_lists[*o].is_empty())?;
+        let ppn = *self.free_lists[from].iter().next().unwrap();
+        self.free_lists[from].remove(&ppn);
+        // the upper halves stay free
+        for o in (order..from).rev() {
+            self.free_lists[o].insert(ppn + (1 << o));
+        }
+        Some(ppn)
+    }
+    /// Free a block of 2^order frames and merge it with its free buddies.
+    fn free_block(&mut self, mut ppn: usize, mut order: usize) {
+        while order < MAX_ORDER - 1 {
+            // a free buddy always lies within [base, end)
+            let buddy = ppn ^ (1 << order);
+            if !self.free_lists[order].remove(&buddy) {
+                break;
+            }
+            ppn = ppn.min(buddy);
+            order += 1;
+        }
+        self.free_lists[order].insert(ppn);
     }
 }
 
+/// The smallest order whose blocks hold `count` frames.
+fn order_of(count: usize) -> usize {
+    count.next_power_of_two().trailing_zeros() as usize
+}
+
 #[derive(Debug)]
 pub struct FrameTracker {
     pub ppn: PhysPageNum,
@@ -70,13 +143,21 @@ impl FrameTracker {
     }
 }
 
+/// Another reference to the same frame, the content is shared rather than copied.
+impl Clone for FrameTracker {
+    fn clone(&self) -> Self {
+        FRAME_ALLOCATOR.exclusive_access().add_ref(self.ppn);
+        Self { ppn: self.ppn }
+    }
+}
+
 impl Drop for FrameTracker {
     fn drop(&mut self) {
         frame_dealloc(self.ppn);
     }
 }
 
-type FrameAllocatorImpl = StackFrameAllocator;
+type FrameAllocatorImpl = BuddyFrameAllocator;
 
 lazy_static! {
     pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
@@ -93,17 +174,49 @@ pub fn init_frame_allocator() {
     );
 }
 
+/// Evict user pages to the swap area when running out of frames.
 pub fn frame_alloc() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .alloc()
-        .map(|ppn| FrameTracker::new(ppn))
+    loop {
+        let ppn = FRAME_ALLOCATOR.exclusive_access().alloc();
+        if let Some(ppn) = ppn {
+            return Some(FrameTracker::new(ppn));
+        }
+        if !swap_out_one() {
+            return None;
+        }
+    }
+}
+
+/// Allocate `count` physically contiguous frames aligned to `align` frames, e.g. for DMA
+/// buffers or huge pages. Each frame is tracked and freed on its own.
+pub fn frame_alloc_contiguous(count: usize, align: usize) -> Option<Vec<FrameTracker>> {
+    loop {
+        let ppn = FRAME_ALLOCATOR.exclusive_access().alloc_contiguous(count, align);
+        if let Some(ppn) = ppn {
+            return Some((ppn.0..ppn.0 + count).map(|ppn| FrameTracker::new(ppn.into())).collect());
+        }
+        // evicting may not free a run that is long enough, but it is all we can do
+        if !swap_out_one() {
+            return None;
+        }
+    }
 }
 
 fn frame_dealloc(ppn: PhysPageNum) {
     FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
 }
 
+/// Another reference to an allocated frame, which keeps it from being freed or swapped out.
+pub fn frame_share(ppn: PhysPageNum) -> FrameTracker {
+    FRAME_ALLOCATOR.exclusive_access().add_ref(ppn);
+    FrameTracker { ppn }
+}
+
+/// How many FrameTrackers refer to the frame.
+pub fn frame_ref_count(ppn: PhysPageNum) -> usize {
+    FRAME_ALLOCATOR.exclusive_access().ref_count(ppn)
+}
+
 #[allow(unused)]
 pub fn frame_allocator_test() {
     let mut v: Vec<FrameTracker> = Vec::new();
@@ -119,5 +232,11 @@ pub fn frame_allocator_test() {
         v.push(frame);
     }
     drop(v);
+    let run = frame_alloc_contiguous(5, 8).unwrap();
+    assert_eq!(run[0].ppn.0 % 8, 0);
+    for (i, frame) in run.iter().enumerate() {
+        assert_eq!(frame.ppn.0, run[0].ppn.0 + i);
+    }
+    drop(run);
     println!("frame_allocator_test passed!");
 }
\ No newline at end of file
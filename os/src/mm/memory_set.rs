@@ -1,11 +1,15 @@
-use crate::config::{MEMORY_END, PAGE_SIZE, PAGE_SIZE_BITS, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::config::{MEMORY_END, MMIO, MMAP_TOP, PAGE_TABLE_LEVELS, SWAP_BASE, SWAP_SIZE, PAGE_SIZE, PAGE_SIZE_BITS, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_LIMIT, USER_STACK_SIZE};
+use crate::mm::asid::{Asid, ASID_ALLOCATOR, ASID_SHIFT};
 use crate::mm::address::{PhysPageNum, StepByOne, VPNRange, VirtAddr, VirtPageNum, PhysAddr};
-use crate::mm::frame_allocator::{frame_alloc, FrameTracker};
-use crate::mm::page_table::{PTEFlags, PageTable, PageTableEntry};
+use crate::mm::frame_allocator::{frame_alloc, frame_ref_count, frame_share, FrameTracker};
+use crate::mm::page_cache::file_page;
+use crate::mm::page_table::{level_pages, PTEFlags, PageTable, PageTableEntry};
+use crate::mm::swap::{swap_duplicate, swap_in, swap_register, PageState, UserPage};
 use crate::sync::UPSafeCell;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use easy_fs::Inode;
 use lazy_static::*;
 use riscv::register::satp;
 
@@ -13,6 +17,10 @@ use riscv::register::satp;
 pub enum MapType {
     Identical,
     Framed,
+    /// Framed, but each page gets its frame on the first access.
+    Lazy,
+    /// Pages of a shared memory segment, which keeps them after they are unmapped.
+    Shm,
 }
 
 bitflags! {
@@ -26,9 +34,16 @@ bitflags! {
 
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    data_frames: BTreeMap<VirtPageNum, Arc<UserPage>>,
     map_type: MapType,
     map_perm: MapPermission,
+    /// The most that mprotect may grant, e.g. no W for a shared mapping of a read-only file.
+    max_perm: MapPermission,
+    /// The pages are shared with the other spaces mapping them instead of copied on write,
+    /// they are never swapped out.
+    shared: bool,
+    /// The file and the offset the area maps from, its pages are written back if shared.
+    file: Option<(Arc<Inode>, usize)>,
 }
 
 impl MapArea {
@@ -45,19 +60,69 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            max_perm: MapPermission::all(),
+            shared: false,
+            file: None,
+        }
+    }
+    pub fn from_another(another: &MapArea) -> Self {
+        Self {
+            vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
+            data_frames: BTreeMap::new(),
+            map_type: another.map_type,
+            map_perm: another.map_perm,
+            max_perm: another.max_perm,
+            shared: another.shared,
+            file: another.file.clone(),
         }
     }
     pub fn map(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+        match self.map_type {
+            MapType::Lazy => {}
+            MapType::Identical => {
+                for (vpn, level) in self.huge_pages() {
+                    page_table.map_huge(vpn, PhysPageNum(vpn.0), self.pte_flags(), level);
+                }
+            }
+            _ => {
+                for vpn in self.vpn_range {
+                    if !self.map_one(page_table, vpn) {
+                        panic!("MapArea map: no frame left for {:?}", vpn);
+                    }
+                }
+            }
         }
     }
     #[allow(unused)]
     pub fn unmap(&mut self, page_table: &mut PageTable) {
+        if self.map_type == MapType::Identical {
+            for (vpn, level) in self.huge_pages() {
+                page_table.unmap_huge(vpn, level);
+            }
+            return;
+        }
         for vpn in self.vpn_range {
             self.unmap_one(page_table, vpn);
         }
     }
+    /// Split an identical area into pages as large as the alignment allows,
+    /// return the first page number and the level of the leaf of each.
+    fn huge_pages(&self) -> Vec<(VirtPageNum, usize)> {
+        let mut pages = Vec::new();
+        let mut vpn = self.vpn_range.get_start();
+        let end = self.vpn_range.get_end();
+        while vpn < end {
+            let level = (0..PAGE_TABLE_LEVELS)
+                .find(|level| {
+                    let count = level_pages(*level);
+                    vpn.0 % count == 0 && vpn.0 + count <= end.0
+                })
+                .unwrap();
+            pages.push((vpn, level));
+            vpn = VirtPageNum(vpn.0 + level_pages(level));
+        }
+        pages
+    }
     /// data: start-aligned but maybe with shorter length
     /// assume that all frames were cleared before
     pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) {
@@ -80,35 +145,214 @@ impl MapArea {
             current_vpn.step();
         }
     }
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+    /// Map `vpn` to its frame, return false if there is no frame left for it.
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
         let ppn: PhysPageNum;
         match self.map_type {
             MapType::Identical => {
                 ppn = PhysPageNum(vpn.0);
             }
-            MapType::Framed => {
-                let frame = frame_alloc().unwrap();
-                ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+            MapType::Shm => {
+                ppn = self.data_frames.get(&vpn).unwrap().ppn().unwrap();
+            }
+            MapType::Framed | MapType::Lazy => {
+                if let Some((inode, offset)) = &self.file {
+                    let index = offset / PAGE_SIZE + (vpn.0 - self.vpn_range.get_start().0);
+                    let page = match file_page(inode, index) {
+                        Some(page) => page,
+                        None => return false,
+                    };
+                    if !self.shared {
+                        // copy on write from the page of the file
+                        let frame = frame_share(page.ppn().unwrap());
+                        drop(page);
+                        self.map_shared(page_table, vpn, frame);
+                        return true;
+                    }
+                    ppn = page.ppn().unwrap();
+                    self.data_frames.insert(vpn, page);
+                } else {
+                    let frame = match frame_alloc() {
+                        Some(frame) => frame,
+                        None => return false,
+                    };
+                    ppn = frame.ppn;
+                    self.data_frames.insert(vpn, UserPage::new(frame));
+                }
             }
         }
-        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
-        page_table.map(vpn, ppn, pte_flags);
+        page_table.map(vpn, ppn, self.pte_flags());
+        true
+    }
+    fn pte_flags(&self) -> PTEFlags {
+        PTEFlags::from_bits(self.map_perm.bits as u16).unwrap()
+    }
+    /// Map `frame` shared with another area, writable pages become read-only until copied on write.
+    fn map_shared(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, frame: FrameTracker) {
+        let mut pte_flags = self.pte_flags();
+        if pte_flags.contains(PTEFlags::W) {
+            pte_flags = (pte_flags - PTEFlags::W) | PTEFlags::COW;
+        }
+        page_table.map(vpn, frame.ppn, pte_flags);
+        self.data_frames.insert(vpn, UserPage::new(frame));
+    }
+    /// Give the area its own copy of a COW page, or take the page back if nobody else shares it.
+    /// Return false if there is no frame left for the copy.
+    fn copy_on_write(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        let page = self.data_frames.get(&vpn).unwrap();
+        let ppn = page.ppn().unwrap();
+        if frame_ref_count(ppn) > 1 {
+            // a shared frame is never swapped out, so it survives the allocation
+            let new_frame = match frame_alloc() {
+                Some(frame) => frame,
+                None => return false,
+            };
+            new_frame
+                .ppn
+                .get_bytes_array()
+                .copy_from_slice(ppn.get_bytes_array());
+            page_table.unmap(vpn);
+            page_table.map(vpn, new_frame.ppn, self.pte_flags());
+            // drop our reference to the shared frame
+            *page.exclusive_access() = PageState::Resident(new_frame);
+        } else {
+            page_table.set_flags(vpn, self.pte_flags());
+        }
+        true
     }
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
         match self.map_type {
-            MapType::Framed => {
-                self.data_frames.remove(&vpn);
+            MapType::Identical => {
+                page_table.unmap(vpn);
+            }
+            MapType::Framed | MapType::Lazy | MapType::Shm => {
+                // pages of a lazy area may not be populated yet,
+                // dropping a swapped out page frees its slot
+                if self.data_frames.contains_key(&vpn) {
+                    self.write_back(page_table, vpn);
+                    self.data_frames.remove(&vpn);
+                    page_table.unmap(vpn);
+                }
             }
-            _ => {}
         }
-        page_table.unmap(vpn);
+    }
+    /// Bring a swapped out page back into a new frame, return false if there is none left.
+    fn swap_in_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        let frame = match frame_alloc() {
+            Some(frame) => frame,
+            None => return false,
+        };
+        let ppn = frame.ppn;
+        swap_in(self.data_frames.get(&vpn).unwrap(), frame);
+        page_table.map(vpn, ppn, self.pte_flags());
+        true
+    }
+    /// Let the swap manager evict a page of a user area, shared pages stay resident.
+    fn register_page(&self, token: usize, vpn: VirtPageNum) {
+        if self.map_perm.contains(MapPermission::U) && !self.shared {
+            swap_register(self.data_frames.get(&vpn).unwrap(), token, vpn);
+        }
+    }
+    fn register_pages(&self, token: usize) {
+        for vpn in self.data_frames.keys() {
+            self.register_page(token, *vpn);
+        }
+    }
+    /// Write a dirty page of a shared file mapping back to the file, which never grows by it.
+    fn write_back(&self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        let (inode, offset) = match &self.file {
+            Some(file) if self.shared => file,
+            _ => return,
+        };
+        let pte = match page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() && pte.flags().contains(PTEFlags::D) => pte,
+            _ => return,
+        };
+        let pos = offset + ((vpn.0 - self.vpn_range.get_start().0) << PAGE_SIZE_BITS);
+        let size = inode.size();
+        if pos < size {
+            let len = PAGE_SIZE.min(size - pos);
+            inode.write_at(pos, &pte.ppn().get_bytes_array()[..len]);
+        }
+        page_table.set_flags(vpn, pte.flags() - PTEFlags::D);
+    }
+    /// Rewrite the PTEs of the resident pages after `map_perm` has changed,
+    /// pages shared copy-on-write stay read-only until they are copied.
+    fn update_pte_flags(&self, page_table: &mut PageTable) {
+        for vpn in self.data_frames.keys() {
+            let pte = page_table.translate(*vpn).unwrap();
+            if !pte.is_valid() {
+                // swapped out, it gets the new flags when swapped in
+                continue;
+            }
+            let mut pte_flags = self.pte_flags() | (pte.flags() & (PTEFlags::A | PTEFlags::D));
+            if pte.is_cow() {
+                pte_flags = (pte_flags - PTEFlags::W) | PTEFlags::COW;
+            }
+            page_table.set_flags(*vpn, pte_flags);
+        }
+    }
+    /// Grow the area up to `new_end`, the new pages of a lazy area are populated on access.
+    /// Return false and leave the area as it was if there are not enough frames.
+    fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) -> bool {
+        let old_end = self.vpn_range.get_end();
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+        if self.map_type != MapType::Lazy {
+            for vpn in VPNRange::new(old_end, new_end) {
+                if !self.map_one(page_table, vpn) {
+                    self.shrink_to(page_table, old_end);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+    /// Grow the area down to `new_start`.
+    /// Return false and leave the area as it was if there are not enough frames.
+    fn prepend_to(&mut self, page_table: &mut PageTable, new_start: VirtPageNum) -> bool {
+        let old_start = self.vpn_range.get_start();
+        self.vpn_range = VPNRange::new(new_start, self.vpn_range.get_end());
+        if self.map_type != MapType::Lazy {
+            for vpn in VPNRange::new(new_start, old_start) {
+                if !self.map_one(page_table, vpn) {
+                    for vpn in VPNRange::new(new_start, vpn) {
+                        self.unmap_one(page_table, vpn);
+                    }
+                    self.vpn_range = VPNRange::new(old_start, self.vpn_range.get_end());
+                    return false;
+                }
+            }
+        }
+        true
+    }
+    /// Cut the area down to `new_end`, freeing the pages beyond it.
+    fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
+            self.unmap_one(page_table, vpn);
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+    }
+    /// Cut the area at `vpn`, return the part starting from it.
+    fn split_off(&mut self, vpn: VirtPageNum) -> Self {
+        let mut right = Self::from_another(self);
+        right.vpn_range = VPNRange::new(vpn, self.vpn_range.get_end());
+        right.data_frames = self.data_frames.split_off(&vpn);
+        if let Some((_, offset)) = &mut right.file {
+            *offset += (vpn.0 - self.vpn_range.get_start().0) << PAGE_SIZE_BITS;
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), vpn);
+        right
     }
 }
 
 pub struct MemorySet {
     page_table: PageTable,
-    areas: Vec<MapArea>,
+    /// by the start of the areas, which never overlap
+    areas: BTreeMap<VirtPageNum, MapArea>,
+    /// [the lowest page the user stack may grow to, stack top)
+    user_stack: Option<(VirtPageNum, VirtPageNum)>,
+    /// None for the kernel space, which always uses ASID 0
+    asid: Option<Asid>,
 }
 
 extern "C" {
@@ -125,11 +369,33 @@ extern "C" {
 }
 
 impl MemorySet {
-    pub fn activate(&self) {
-        let satp = self.page_table.token();
+    pub fn activate(&mut self) {
+        let satp = self.satp();
         unsafe {
             satp::write(satp);
-            asm!("sfence.vma");
+            asm!("sfence.vma x0, {}", in(reg) self.asid_id());
+        }
+    }
+    /// The satp value to switch to this space, tagged with an ASID of the current generation.
+    pub fn satp(&mut self) -> usize {
+        if let Some(asid) = self.asid.as_mut() {
+            ASID_ALLOCATOR.exclusive_access().refresh(asid);
+        }
+        self.page_table.token() | self.asid_id() << ASID_SHIFT
+    }
+    fn asid_id(&self) -> usize {
+        self.asid.map_or(0, |asid| asid.id())
+    }
+    /// Drop the TLB entries of this space after its page table has changed.
+    fn flush_tlb(&self) {
+        unsafe {
+            asm!("sfence.vma x0, {}", in(reg) self.asid_id());
+        }
+    }
+    fn flush_tlb_page(&self, vpn: VirtPageNum) {
+        let va: VirtAddr = vpn.into();
+        unsafe {
+            asm!("sfence.vma {}, {}", in(reg) va.0, in(reg) self.asid_id());
         }
     }
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
@@ -141,7 +407,9 @@ impl MemorySet {
     pub fn new_bare() -> Self {
         Self {
             page_table: PageTable::new(),
-            areas: Vec::new(),
+            areas: BTreeMap::new(),
+            user_stack: None,
+            asid: Some(Asid::new()),
         }
     }
     fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
@@ -149,7 +417,37 @@ impl MemorySet {
         if let Some(data) = data {
             map_area.copy_data(&mut self.page_table, data);
         }
-        self.areas.push(map_area);
+        // not before the data is copied, which finds the frames by the page table
+        map_area.register_pages(self.page_table.token());
+        self.put_area(map_area);
+    }
+    pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
+        if let Some(mut area) = self.areas.remove(&start_vpn) {
+            area.unmap(&mut self.page_table);
+            self.flush_tlb();
+        }
+    }
+    /// The start of the first area which may overlap [start_vpn, ..), areas before it end before `start_vpn`.
+    fn first_area_from(&self, start_vpn: VirtPageNum) -> VirtPageNum {
+        match self.areas.range(..=start_vpn).next_back() {
+            Some((vpn, area)) if area.vpn_range.get_end() > start_vpn => *vpn,
+            _ => start_vpn,
+        }
+    }
+    /// Take the areas overlapping [start_vpn, end_vpn) out of the set, empty ones overlap nothing.
+    fn take_areas(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> Vec<MapArea> {
+        let first = self.first_area_from(start_vpn);
+        let keys: Vec<VirtPageNum> = self
+            .areas
+            .range(first..end_vpn)
+            .filter(|(vpn, a)| a.vpn_range.get_end() > **vpn)
+            .map(|(vpn, _)| *vpn)
+            .collect();
+        keys.iter().map(|vpn| self.areas.remove(vpn).unwrap()).collect()
+    }
+    /// Put an area taken out back.
+    fn put_area(&mut self, map_area: MapArea) {
+        self.areas.insert(map_area.vpn_range.get_start(), map_area);
     }
     /// Assume that no conflicts.
     pub fn insert_framed_area(
@@ -159,45 +457,251 @@ impl MemorySet {
         permission: MapPermission,
     ) -> isize {
         trace!("MemorySet insert_framed_area start_va:{:#x}, end_va:{:#x}, permission:{}", start_va.0, end_va.0, permission.bits);
+        self.insert_area(MapArea::new(start_va, end_va, MapType::Framed, permission))
+    }
+    /// Map an area for mmap, no frame is allocated until the pages are accessed.
+    /// `file` gives the file and the offset to map, the area is anonymous without it.
+    pub fn insert_mmap_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+        max_permission: MapPermission,
+        shared: bool,
+        file: Option<(Arc<Inode>, usize)>,
+    ) -> isize {
+        trace!("MemorySet insert_mmap_area start_va:{:#x}, end_va:{:#x}, permission:{}, shared:{}", start_va.0, end_va.0, permission.bits, shared);
+        let mut map_area = MapArea::new(start_va, end_va, MapType::Lazy, permission);
+        map_area.max_perm = max_permission;
+        map_area.shared = shared;
+        let anonymous_shared = shared && file.is_none();
+        map_area.file = file;
+        let len = self.insert_area(map_area);
+        if len < 0 || !anonymous_shared {
+            return len;
+        }
+        // fork only shares the pages which exist, so they can not be populated lazily
         let start_vpn = start_va.floor();
-        let end_vpn = end_va.ceil();
-        if let Some(_area) = self.areas.iter().find(|a| {
-            a.vpn_range.get_end() > start_vpn && a.vpn_range.get_start() < end_vpn
+        let area = self.areas.get_mut(&start_vpn).unwrap();
+        for vpn in area.vpn_range {
+            if !area.map_one(&mut self.page_table, vpn) {
+                warn!("MemorySet no frame left for the shared area at {:?}", start_vpn);
+                self.remove_area_with_start_vpn(start_vpn);
+                return -1;
+            }
+        }
+        len
+    }
+    /// Attach the pages of a shared memory segment at `start_va`, return the length.
+    pub fn insert_shm_area(
+        &mut self,
+        start_va: VirtAddr,
+        pages: Vec<Arc<UserPage>>,
+        permission: MapPermission,
+    ) -> isize {
+        let end_va = VirtAddr::from(start_va.0 + (pages.len() << PAGE_SIZE_BITS));
+        trace!("MemorySet insert_shm_area start_va:{:#x}, end_va:{:#x}, permission:{}", start_va.0, end_va.0, permission.bits);
+        let mut map_area = MapArea::new(start_va, end_va, MapType::Shm, permission);
+        // SHM_RDONLY stays read-only
+        map_area.max_perm = permission;
+        map_area.shared = true;
+        map_area.data_frames = map_area.vpn_range.into_iter().zip(pages).collect();
+        self.insert_area(map_area)
+    }
+    /// Detach the shared memory segment attached at `start_va`.
+    pub fn remove_shm_area(&mut self, start_va: VirtAddr) -> isize {
+        trace!("MemorySet remove_shm_area start_va:{:#x}", start_va.0);
+        let start_vpn = start_va.floor();
+        match self.areas.get(&start_vpn) {
+            Some(area) if area.map_type == MapType::Shm => {}
+            _ => {
+                warn!("MemorySet no shared memory is attached at {:?}", start_vpn);
+                return -1;
+            }
+        }
+        self.remove_area_with_start_vpn(start_vpn);
+        0
+    }
+    fn insert_area(&mut self, map_area: MapArea) -> isize {
+        let start_vpn = map_area.vpn_range.get_start();
+        let end_vpn = map_area.vpn_range.get_end();
+        // the last area starting before the end is the only one which may overlap,
+        // an empty one (the heap) still owns its start
+        if self.areas.contains_key(&start_vpn) || self.areas.range(..end_vpn).next_back().map_or(false, |(_, a)| {
+            a.vpn_range.get_end() > start_vpn
         }) {
             warn!("MemorySet area {:?}-{:?} are mapped before mapping", start_vpn, end_vpn);
             return -1;
         }
-        self.push(
-            MapArea::new(start_va, end_va, MapType::Framed, permission),
-            None,
-        );
+        self.push(map_area, None);
         ((end_vpn.0 - start_vpn.0) << PAGE_SIZE_BITS) as isize
     }
+    /// Whether the user may map [start, start + len): below `MMAP_TOP`, which keeps the
+    /// trampoline and the trap context out of reach, and out of the room of the user stack.
+    pub fn is_user_range_allowed(&self, start: usize, len: usize) -> bool {
+        let end = match start.checked_add(len) {
+            Some(end) if end <= MMAP_TOP => end,
+            _ => return false,
+        };
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        self.user_stack
+            .map_or(true, |(limit, top)| end_vpn <= limit || start_vpn >= top)
+    }
+    /// Find `len` bytes of user space which are not mapped between the user stack and `MMAP_TOP`,
+    /// searching down from the top so that the heap keeps its room to grow.
+    pub fn find_free_area(&self, len: usize) -> Option<VirtAddr> {
+        let page_count = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        if page_count == 0 {
+            return None;
+        }
+        // the elf and the room of the user stack are below it, the page at 0 is never handed out
+        let bottom = self.user_stack.map_or(VirtPageNum(1), |(_, top)| top);
+        let mut end = VirtAddr::from(MMAP_TOP).floor();
+        // try the hole below `end`, then move below the area bounding it
+        loop {
+            let (hole_start, next_end) = match self.areas.range(..end).next_back() {
+                Some((start_vpn, a)) => (a.vpn_range.get_end().max(bottom), *start_vpn),
+                None => (bottom, bottom),
+            };
+            if end.0 >= hole_start.0 + page_count {
+                return Some(VirtPageNum(end.0 - page_count).into());
+            }
+            if next_end <= bottom {
+                return None;
+            }
+            end = next_end;
+        }
+    }
+    /// Whether [start_vpn, end_vpn) is covered by user areas without holes.
+    fn is_user_range_mapped(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        let first = self.first_area_from(start_vpn);
+        let mut covered_vpn = start_vpn;
+        for a in self.areas.range(first..end_vpn).map(|(_, a)| a).filter(|a| a.map_perm.contains(MapPermission::U)) {
+            if a.vpn_range.get_end() <= covered_vpn { continue; }
+            if a.vpn_range.get_start() > covered_vpn { break; }
+            covered_vpn = a.vpn_range.get_end();
+            if covered_vpn >= end_vpn { break; }
+        }
+        covered_vpn >= end_vpn
+    }
     pub fn remove_frame_area(&mut self, start_va: VirtAddr, end_va: VirtAddr) -> isize {
         trace!("MemorySet remove_frame_area start_va:{:#x}, end_va:{:#x}", start_va.0, end_va.0);
         let start_vpn = start_va.floor();
         let end_vpn = end_va.ceil();
-        let mut unmap_vpn = start_vpn;
-        self.areas.sort_by(|l, r| { l.vpn_range.get_start().cmp(&r.vpn_range.get_start())});
-        for a in &mut self.areas {
-            if a.vpn_range.get_start() >= end_vpn { break; }
-            if a.vpn_range.get_end() <= unmap_vpn { continue; }
-            let limit_vpn = end_vpn.min(a.vpn_range.get_end());
-            for vpn in VPNRange::new(unmap_vpn, limit_vpn) {
+        if !self.is_user_range_mapped(start_vpn, end_vpn) {
+            warn!("MemorySet area {:?}-{:?} are not mapped before unmapping", start_vpn, end_vpn);
+            return -1;
+        }
+        // unmap the range and keep what is left of the areas on both sides
+        for mut a in self.take_areas(start_vpn, end_vpn) {
+            let l = start_vpn.max(a.vpn_range.get_start());
+            let r = end_vpn.min(a.vpn_range.get_end());
+            for vpn in VPNRange::new(l, r) {
                 a.unmap_one(&mut self.page_table, vpn);
             }
-            unmap_vpn = limit_vpn;
-            if unmap_vpn == end_vpn { break; }
+            if r < a.vpn_range.get_end() {
+                self.put_area(a.split_off(r));
+            }
+            if a.vpn_range.get_start() < l {
+                a.vpn_range = VPNRange::new(a.vpn_range.get_start(), l);
+                self.put_area(a);
+            }
         }
-        self.areas.retain(|a| { !a.data_frames.is_empty() });
-        if unmap_vpn == end_vpn {
-            ((end_vpn.0 - start_vpn.0) << PAGE_SIZE_BITS) as isize
-        } else { -1 }
+        self.flush_tlb();
+        ((end_vpn.0 - start_vpn.0) << PAGE_SIZE_BITS) as isize
+    }
+    /// Move the end of the area starting at `start_va` to `new_end_va`, the pages beyond
+    /// the new end are freed. Return false if the area is gone or would overlap another one.
+    pub fn resize_area(&mut self, start_va: VirtAddr, new_end_va: VirtAddr) -> bool {
+        trace!("MemorySet resize_area start_va:{:#x}, new_end_va:{:#x}", start_va.0, new_end_va.0);
+        let start_vpn = start_va.floor();
+        let new_end_vpn = new_end_va.ceil();
+        let end_vpn = match self.areas.get(&start_vpn) {
+            Some(area) => area.vpn_range.get_end(),
+            None => {
+                warn!("MemorySet area starting at {:?} is not mapped before resizing", start_vpn);
+                return false;
+            }
+        };
+        // an empty area starts where it ends, it does not stand in its own way
+        if new_end_vpn > end_vpn
+            && self.areas.range(end_vpn..new_end_vpn).any(|(vpn, _)| *vpn != start_vpn)
+        {
+            warn!("MemorySet area {:?}-{:?} are mapped before growing", end_vpn, new_end_vpn);
+            return false;
+        }
+        let area = self.areas.get_mut(&start_vpn).unwrap();
+        if new_end_vpn > end_vpn {
+            if !area.append_to(&mut self.page_table, new_end_vpn) {
+                warn!("MemorySet no frame left to grow the area up to {:?}", new_end_vpn);
+                return false;
+            }
+        } else {
+            area.shrink_to(&mut self.page_table, new_end_vpn);
+            self.flush_tlb();
+        }
+        true
+    }
+    /// Change the permission of a mapped user range, splitting the areas at its ends.
+    pub fn protect_area(&mut self, start_va: VirtAddr, end_va: VirtAddr, permission: MapPermission) -> isize {
+        trace!("MemorySet protect_area start_va:{:#x}, end_va:{:#x}, permission:{}", start_va.0, end_va.0, permission.bits);
+        let start_vpn = start_va.floor();
+        let end_vpn = end_va.ceil();
+        if !self.is_user_range_mapped(start_vpn, end_vpn) {
+            warn!("MemorySet area {:?}-{:?} are not mapped before protecting", start_vpn, end_vpn);
+            return -1;
+        }
+        let first = self.first_area_from(start_vpn);
+        if self.areas.range(first..end_vpn).any(|(_, a)| !a.max_perm.contains(permission)) {
+            warn!("MemorySet area {:?}-{:?} may not get permission {}", start_vpn, end_vpn, permission.bits);
+            return -1;
+        }
+        for mut a in self.take_areas(start_vpn, end_vpn) {
+            let l = start_vpn.max(a.vpn_range.get_start());
+            let r = end_vpn.min(a.vpn_range.get_end());
+            let mut middle = if a.vpn_range.get_start() < l {
+                let middle = a.split_off(l);
+                self.put_area(a);
+                middle
+            } else {
+                a
+            };
+            if r < middle.vpn_range.get_end() {
+                self.put_area(middle.split_off(r));
+            }
+            middle.map_perm = permission;
+            middle.update_pte_flags(&mut self.page_table);
+            self.put_area(middle);
+        }
+        // drop the TLB entries with the old permission
+        self.flush_tlb();
+        ((end_vpn.0 - start_vpn.0) << PAGE_SIZE_BITS) as isize
+    }
+    /// Write the dirty pages of the shared file mappings in a mapped user range back to the files.
+    pub fn sync_area(&mut self, start_va: VirtAddr, end_va: VirtAddr) -> isize {
+        trace!("MemorySet sync_area start_va:{:#x}, end_va:{:#x}", start_va.0, end_va.0);
+        let start_vpn = start_va.floor();
+        let end_vpn = end_va.ceil();
+        if !self.is_user_range_mapped(start_vpn, end_vpn) {
+            warn!("MemorySet area {:?}-{:?} are not mapped before syncing", start_vpn, end_vpn);
+            return -1;
+        }
+        let first = self.first_area_from(start_vpn);
+        for a in self.areas.range(first..end_vpn).map(|(_, a)| a) {
+            for vpn in a.data_frames.range(start_vpn..end_vpn).map(|(vpn, _)| *vpn) {
+                a.write_back(&mut self.page_table, vpn);
+            }
+        }
+        // the D bits are cleared, later writes must set them again
+        self.flush_tlb();
+        0
     }
 
     /// Without kernel stacks.
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
+        memory_set.asid = None;
         // map trampoline
         memory_set.map_trampoline();
         // map kernel sections
@@ -258,11 +762,33 @@ impl MemorySet {
             ),
             None,
         );
+        println!("mapping swap area");
+        memory_set.push(
+            MapArea::new(
+                SWAP_BASE.into(),
+                (SWAP_BASE + SWAP_SIZE).into(),
+                MapType::Identical,
+                MapPermission::R | MapPermission::W,
+            ),
+            None,
+        );
+        println!("mapping memory-mapped registers");
+        for &(start, len) in MMIO {
+            memory_set.push(
+                MapArea::new(
+                    start.into(),
+                    (start + len).into(),
+                    MapType::Identical,
+                    MapPermission::R | MapPermission::W,
+                ),
+                None,
+            );
+        }
         memory_set
     }
     /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// also returns user_sp, the bottom of the (empty) heap and entry point.
+    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize, usize) {
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
@@ -297,12 +823,17 @@ impl MemorySet {
                 );
             }
         }
-        // map user stack with U flags
+        // map user stack with U flags, leaving room for it to grow down
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
+        let mut user_stack_limit: usize = max_end_va.into();
         // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        user_stack_limit += PAGE_SIZE;
+        let user_stack_top = user_stack_limit + USER_STACK_LIMIT;
+        let user_stack_bottom = user_stack_top - USER_STACK_SIZE;
+        memory_set.user_stack = Some((
+            VirtAddr::from(user_stack_limit).floor(),
+            VirtAddr::from(user_stack_top).floor(),
+        ));
         memory_set.push(
             MapArea::new(
                 user_stack_bottom.into(),
@@ -312,6 +843,17 @@ impl MemorySet {
             ),
             None,
         );
+        // map an empty heap above another guard page, it grows through sbrk
+        let heap_bottom = user_stack_top + PAGE_SIZE;
+        memory_set.push(
+            MapArea::new(
+                heap_bottom.into(),
+                heap_bottom.into(),
+                MapType::Lazy,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            ),
+            None,
+        );
         // map TrapContext
         memory_set.push(
             MapArea::new(
@@ -325,9 +867,179 @@ impl MemorySet {
         (
             memory_set,
             user_stack_top,
+            heap_bottom,
             elf.header.pt2.entry_point() as usize,
         )
     }
+    /// Share a user space page by page, only the pages which are still mapped.
+    ///
+    /// User pages are shared copy-on-write by both spaces, the trap context is copied
+    /// since the kernel writes it without going through the page table.
+    /// Return None if the swap area has no room for the child's copies of swapped out pages.
+    pub fn from_existed_user(user_space: &mut MemorySet) -> Option<MemorySet> {
+        let mut memory_set = Self::new_bare();
+        memory_set.user_stack = user_space.user_stack;
+        // map trampoline
+        memory_set.map_trampoline();
+        // share data sections/user_stack, copy trap_context
+        for area in user_space.areas.values() {
+            let mut new_area = MapArea::from_another(area);
+            if area.shared {
+                // shared pages are always resident
+                for (vpn, page) in area.data_frames.iter() {
+                    memory_set.page_table.map(*vpn, page.ppn().unwrap(), area.pte_flags());
+                    new_area.data_frames.insert(*vpn, page.clone());
+                }
+            } else if area.map_perm.contains(MapPermission::U) {
+                for (vpn, page) in area.data_frames.iter() {
+                    let frame = match &*page.exclusive_access() {
+                        PageState::Resident(frame) => Some(frame.clone()),
+                        PageState::Swapped(slot) => match swap_duplicate(slot) {
+                            // the child gets its own copy in the swap area
+                            Some(slot) => {
+                                memory_set.page_table.set_swapped(*vpn, slot.id());
+                                new_area.data_frames.insert(*vpn, UserPage::new_swapped(slot));
+                                None
+                            }
+                            None => {
+                                warn!("MemorySet fork failed: swap area is full");
+                                // the pages shared so far stay copy-on-write in the parent
+                                user_space.flush_tlb();
+                                return None;
+                            }
+                        },
+                    };
+                    if let Some(frame) = frame {
+                        new_area.map_shared(&mut memory_set.page_table, *vpn, frame);
+                        // the parent loses its write permission as well
+                        let pte_flags = memory_set.page_table.translate(*vpn).unwrap().flags();
+                        user_space.page_table.set_flags(*vpn, pte_flags);
+                    }
+                }
+                new_area.register_pages(memory_set.page_table.token());
+            } else {
+                for vpn in area.data_frames.keys() {
+                    if !new_area.map_one(&mut memory_set.page_table, *vpn) {
+                        warn!("MemorySet fork failed: no frame left");
+                        user_space.flush_tlb();
+                        return None;
+                    }
+                    let src_ppn = user_space.page_table.translate(*vpn).unwrap().ppn();
+                    let dst_ppn = memory_set.translate(*vpn).unwrap().ppn();
+                    dst_ppn
+                        .get_bytes_array()
+                        .copy_from_slice(src_ppn.get_bytes_array());
+                }
+            }
+            memory_set.put_area(new_area);
+        }
+        // the parent may still write its pages through the TLB
+        user_space.flush_tlb();
+        Some(memory_set)
+    }
+    /// Try to resolve a page fault at `va` caused by an `access` of R, W or X,
+    /// return false if it is a real access violation.
+    pub fn handle_page_fault(&mut self, va: VirtAddr, access: MapPermission) -> bool {
+        let handled = self.resolve_page_fault(va.floor(), access);
+        if handled {
+            // the faulting entry may be cached
+            self.flush_tlb_page(va.floor());
+        }
+        handled
+    }
+    fn resolve_page_fault(&mut self, vpn: VirtPageNum, access: MapPermission) -> bool {
+        let area = match self.areas.range_mut(..=vpn).next_back() {
+            Some((_, area)) if vpn < area.vpn_range.get_end() => area,
+            _ => return access != MapPermission::X && self.grow_user_stack(vpn),
+        };
+        if !area.map_perm.contains(access | MapPermission::U) {
+            return false;
+        }
+        let token = self.page_table.token();
+        match self.page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() => {
+                if access == MapPermission::W && pte.is_cow() {
+                    area.copy_on_write(&mut self.page_table, vpn)
+                } else if !pte.flags().contains(PTEFlags::A)
+                    || (access == MapPermission::W && !pte.flags().contains(PTEFlags::D))
+                {
+                    // the hardware may fault instead of setting A and D itself
+                    let mut pte_flags = pte.flags() | PTEFlags::A;
+                    if access == MapPermission::W {
+                        pte_flags |= PTEFlags::D;
+                    }
+                    self.page_table.set_flags(vpn, pte_flags);
+                    true
+                } else {
+                    false
+                }
+            }
+            Some(pte) if pte.is_swapped() => {
+                if !area.swap_in_one(&mut self.page_table, vpn) {
+                    return false;
+                }
+                area.register_page(token, vpn);
+                true
+            }
+            _ => {
+                if area.map_type == MapType::Lazy && area.map_one(&mut self.page_table, vpn) {
+                    area.register_page(token, vpn);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+    /// Grow the user stack down to `vpn` if it is within the limit.
+    fn grow_user_stack(&mut self, vpn: VirtPageNum) -> bool {
+        let (limit, top) = match self.user_stack {
+            Some(range) => range,
+            None => return false,
+        };
+        if vpn < limit || vpn >= top {
+            return false;
+        }
+        let bottom = match self.areas.range(..top).next_back() {
+            Some((bottom, stack)) if stack.vpn_range.get_end() == top => *bottom,
+            _ => return false,
+        };
+        // something else may have been mapped in the room of the stack
+        if vpn >= bottom || self.areas.range(..bottom).next_back().map_or(false, |(_, a)| {
+            a.vpn_range.get_end() > vpn
+        }) {
+            return false;
+        }
+        let token = self.page_table.token();
+        let mut stack = self.areas.remove(&bottom).unwrap();
+        // its start moves
+        let grown = stack.prepend_to(&mut self.page_table, vpn);
+        if grown {
+            // the pages above are registered already
+            for vpn in VPNRange::new(vpn, bottom) {
+                stack.register_page(token, vpn);
+            }
+        }
+        self.put_area(stack);
+        grown
+    }
+    /// Whether a fault at `va` hits the guard page below the limit of the user stack.
+    pub fn is_stack_overflow(&self, va: VirtAddr) -> bool {
+        match self.user_stack {
+            Some((limit, _)) => va.floor().0 + 1 == limit.0,
+            None => false,
+        }
+    }
+    /// Drop all the user areas and their frames, the page table is kept.
+    /// Shared file mappings are written back first.
+    pub fn recycle_data_pages(&mut self) {
+        for a in self.areas.values() {
+            for vpn in a.data_frames.keys() {
+                a.write_back(&mut self.page_table, *vpn);
+            }
+        }
+        self.areas.clear();
+    }
     /// Mention that trampoline is not collected by areas.
     fn map_trampoline(&mut self) {
         self.page_table.map(
@@ -360,5 +1072,11 @@ pub fn remap_test() {
         kernel_space.page_table.translate(mid_data.floor()).unwrap().executable(),
         false,
     );
+    // physical memory is mapped by huge pages where it is aligned
+    let mid_memory: VirtAddr = ((ekernel as usize + MEMORY_END) / 2).into();
+    assert_eq!(
+        kernel_space.page_table.translate(mid_memory.floor()).unwrap().ppn().0,
+        mid_memory.floor().0,
+    );
     println!("remap_test passed!");
 }
\ No newline at end of file
@@ -1,14 +1,20 @@
 pub mod address;
-mod frame_allocator;
+mod asid;
+pub mod frame_allocator;
 mod heap_allocator;
+mod page_cache;
 mod page_table;
 pub mod memory_set;
+pub mod shm;
+mod swap;
 
-pub use page_table::translated_byte_buffer;
+pub use asid::asid_enabled;
+pub use page_table::{translated_byte_buffer, translated_refmut, translated_str, UserBuffer};
 use crate::mm::memory_set::KERNEL_SPACE;
 
 pub fn init() {
     heap_allocator::init_heap();
     frame_allocator::init_frame_allocator();
     KERNEL_SPACE.exclusive_access().activate();
+    asid::init_asid_allocator();
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use crate::config::PAGE_SIZE;
+use crate::mm::frame_allocator::frame_alloc;
+use crate::mm::swap::UserPage;
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use easy_fs::Inode;
+use lazy_static::*;
+
+/// (block id, block offset) of the disk inode and the page index in the file.
+type PageKey = (usize, usize, usize);
+
+lazy_static! {
+    /// Pages of files mapped by some user space, so that all shared mappings
+    /// of a file page see the same frame.
+    static ref PAGE_CACHE: UPSafeCell<BTreeMap<PageKey, Weak<UserPage>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// The page at `index` of the file, it is read from the file unless still mapped somewhere.
+/// Bytes beyond the end of the file are zero. Return None if there is no frame left for it.
+pub fn file_page(inode: &Arc<Inode>, index: usize) -> Option<Arc<UserPage>> {
+    let (block_id, block_offset) = inode.disk_inode_pos();
+    let key = (block_id, block_offset, index);
+    let cached = PAGE_CACHE.exclusive_access().get(&key).and_then(|page| page.upgrade());
+    if let Some(page) = cached {
+        return Some(page);
+    }
+    // not while holding the cache, the allocation may swap out pages
+    let frame = frame_alloc()?;
+    inode.read_at(index * PAGE_SIZE, frame.ppn.get_bytes_array());
+    let page = UserPage::new(frame);
+    let mut cache = PAGE_CACHE.exclusive_access();
+    cache.retain(|_, page| page.strong_count() > 0);
+    cache.insert(key, Arc::downgrade(&page));
+    Some(page)
+}
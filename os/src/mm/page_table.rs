@@ -1,10 +1,14 @@
+use alloc::string::String;
 use alloc::vec::Vec;
-use crate::mm::address::{PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::config::{PAGE_TABLE_LEVELS, SATP_MODE};
+use crate::mm::address::{PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
 use bitflags::*;
-use crate::mm::frame_allocator::{frame_alloc, FrameTracker};
+use crate::mm::frame_allocator::{frame_alloc, frame_share, FrameTracker};
+use crate::mm::memory_set::MapPermission;
+use crate::task::current_handle_page_fault;
 
 bitflags! {
-    pub struct PTEFlags: u8 {
+    pub struct PTEFlags: u16 {
         const V = 1 << 0;
         const R = 1 << 1;
         const W = 1 << 2;
@@ -13,6 +17,10 @@ bitflags! {
         const G = 1 << 5;
         const A = 1 << 6;
         const D = 1 << 7;
+        /// 软件保留位(RSW)：写时复制的共享页
+        const COW = 1 << 8;
+        /// 软件保留位(RSW)：无效页表项，页面被换出，PPN 字段为交换槽号
+        const SWAPPED = 1 << 9;
     }
 }
 
@@ -35,7 +43,7 @@ impl PageTableEntry {
         (self.bits >> 10 & ((1usize << 44) - 1)).into()
     }
     pub fn flags(&self) -> PTEFlags {
-        PTEFlags::from_bits(self.bits as u8).unwrap()
+        PTEFlags::from_bits_truncate((self.bits & 0x3ff) as u16)
     }
 
     pub fn is_valid(&self) -> bool {
@@ -50,6 +58,22 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+    pub fn is_cow(&self) -> bool {
+        (self.flags() & PTEFlags::COW) != PTEFlags::empty()
+    }
+    pub fn is_swapped(&self) -> bool {
+        !self.is_valid() && (self.flags() & PTEFlags::SWAPPED) != PTEFlags::empty()
+    }
+    /// A valid entry pointing to the next level has none of R, W and X.
+    pub fn is_leaf(&self) -> bool {
+        self.is_valid() && self.flags().intersects(PTEFlags::R | PTEFlags::W | PTEFlags::X)
+    }
+}
+
+/// How many 4KiB pages a leaf at `level` maps, level 0 is the root,
+/// a leaf at the last level maps 4KiB and one level up 2MiB, 1GiB...
+pub fn level_pages(level: usize) -> usize {
+    1 << (9 * (PAGE_TABLE_LEVELS - 1 - level))
 }
 
 pub struct PageTable {
@@ -59,7 +83,7 @@ pub struct PageTable {
 
 impl PageTable {
     pub fn token(&self) -> usize {
-        8usize << 60 | self.root_ppn.0
+        SATP_MODE << 60 | self.root_ppn.0
     }
     pub fn new() -> Self {
         let frame = frame_alloc().unwrap();
@@ -69,15 +93,20 @@ impl PageTable {
         }
     }
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        self.find_pte_create_at(vpn, PAGE_TABLE_LEVELS - 1)
+    }
+    /// The entry of `vpn` at `level`, creating the tables above it.
+    fn find_pte_create_at(&mut self, vpn: VirtPageNum, level: usize) -> Option<&mut PageTableEntry> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
         let mut result: Option<&mut PageTableEntry> = None;
-        for i in 0..3 {
+        for i in 0..=level {
             let pte = &mut ppn.get_pte_array()[idxs[i]];
-            if i == 2 {
+            if i == level {
                 result = Some(pte);
                 break;
             }
+            assert!(!pte.is_leaf(), "vpn {:?} is in a huge page", vpn);
             if !pte.is_valid() {
                 let frame = frame_alloc().unwrap();
                 *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
@@ -94,12 +123,37 @@ impl PageTable {
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
     }
+    /// Map `vpn` with a leaf at `level`, both page numbers aligned to its size.
+    pub fn map_huge(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags, level: usize) {
+        let pages = level_pages(level);
+        assert!(vpn.0 % pages == 0 && ppn.0 % pages == 0, "vpn {:?} is not aligned to a huge page", vpn);
+        let pte = self.find_pte_create_at(vpn, level).unwrap();
+        assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
+    pub fn unmap_huge(&mut self, vpn: VirtPageNum, level: usize) {
+        let pte = self.find_pte_create_at(vpn, level).unwrap();
+        assert!(pte.is_leaf(), "vpn {:?} is invalid before unmapping", vpn);
+        *pte = PageTableEntry::empty();
+    }
     #[allow(unused)]
     pub fn unmap(&mut self, vpn: VirtPageNum) {
         let pte = self.find_pte_create(vpn).unwrap();
-        assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
+        assert!(pte.is_valid() || pte.is_swapped(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
+    /// Invalidate a page whose content is in swap slot `slot`.
+    pub fn set_swapped(&mut self, vpn: VirtPageNum, slot: usize) {
+        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(!pte.is_swapped(), "vpn {:?} is swapped out before swapping out", vpn);
+        *pte = PageTableEntry::new(PhysPageNum(slot), PTEFlags::SWAPPED);
+    }
+    /// Change the flags of a mapped page, keeping its frame.
+    pub fn set_flags(&mut self, vpn: VirtPageNum, flags: PTEFlags) {
+        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before setting flags", vpn);
+        *pte = PageTableEntry::new(pte.ppn(), flags | PTEFlags::V);
+    }
     /// Temporarily used to get arguments from user space.
     pub fn from_token(satp: usize) -> Self {
         Self {
@@ -107,16 +161,17 @@ impl PageTable {
             frames: Vec::new(),
         }
     }
-    fn find_pte(&self, vpn: VirtPageNum) -> Option<&PageTableEntry> {
+    /// The leaf of `vpn` and its level, which is the last one unless it is a huge page.
+    fn find_pte(&self, vpn: VirtPageNum) -> Option<(&PageTableEntry, usize)> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
-        let mut result: Option<&PageTableEntry> = None;
+        let mut result: Option<(&PageTableEntry, usize)> = None;
 
-        for i in 0..3 {
+        for i in 0..PAGE_TABLE_LEVELS {
             let pte = &ppn.get_pte_array()[idxs[i]];
             trace!("find_pte vpn={:#x}, pte={:#x}, level={}", vpn.0, pte.bits, i);
-            if i == 2 {
-                result = Some(pte);
+            if i == PAGE_TABLE_LEVELS - 1 || pte.is_leaf() {
+                result = Some((pte, i));
                 break;
             }
             if !pte.is_valid() {
@@ -127,36 +182,174 @@ impl PageTable {
 
         result
     }
+    /// The entry mapping `vpn`, for a huge page it points to the 4KiB page of `vpn` in it.
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
-        self.find_pte(vpn)
-            .map(|pte| {pte.clone()})
+        self.find_pte(vpn).map(|(pte, level)| {
+            if level == PAGE_TABLE_LEVELS - 1 {
+                *pte
+            } else {
+                let offset = vpn.0 & (level_pages(level) - 1);
+                PageTableEntry::new(PhysPageNum(pte.ppn().0 + offset), pte.flags())
+            }
+        })
+    }
+    pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
+        self.translate(va.clone().floor())
+            .filter(|pte| pte.is_valid())
+            .map(|pte| {
+                let aligned_pa: PhysAddr = pte.ppn().into();
+                (aligned_pa.0 + va.page_offset()).into()
+            })
     }
 }
 
+/// The kernel accesses user memory through physical addresses, so it has to resolve
+/// the page faults of the current task itself, e.g. populate a lazy page or copy a COW
+/// page it is going to write. Return false if the page is not accessible.
+fn prepare_user_page(page_table: &PageTable, va: VirtAddr, access: MapPermission) -> bool {
+    match page_table.translate(va.floor()).filter(|pte| pte.is_valid()) {
+        // e.g. the trap context
+        Some(pte) if !pte.flags().contains(PTEFlags::U) => false,
+        Some(pte) if access == MapPermission::W && pte.is_cow() => current_handle_page_fault(va.0, access),
+        // a read-only frame may be shared with another space or the page cache
+        Some(pte) if access == MapPermission::W => pte.writable(),
+        Some(_) => true,
+        None => current_handle_page_fault(va.0, access),
+    }
+}
+
+/// Translate a user buffer into the slices of the physical pages it covers,
+/// return None if some page of it does not allow `access`.
 pub fn translated_byte_buffer(
     token: usize,
     ptr: *const u8,
-    len: usize
-) -> Vec<&'static [u8]> {
+    len: usize,
+    access: MapPermission,
+) -> Option<Vec<&'static mut [u8]>> {
     let page_table = PageTable::from_token(token);
     let mut start = ptr as usize;
-    let end = start + len;
+    let end = start.checked_add(len)?;
     let mut v = Vec::new();
+    // keep the pages prepared so far from being swapped out by the later ones
+    let mut pinned = Vec::new();
 
     while start < end {
         let start_va = VirtAddr::from(start);
+        if !prepare_user_page(&page_table, start_va, access) {
+            return None;
+        }
         let mut vpn = start_va.floor();
         let ppn = page_table
             .translate(vpn)
-            .unwrap()
+            .filter(|pte| pte.is_valid())?
             .ppn();
+        pinned.push(frame_share(ppn));
         vpn.step();
 
         let mut end_va: VirtAddr = vpn.into();
         end_va = end_va.min(VirtAddr::from(end));
-        v.push(&ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
+        if end_va.page_offset() == 0 {
+            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
+        } else {
+            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
+        }
         start = end_va.into();
     }
 
-    v
-}
\ No newline at end of file
+    Some(v)
+}
+
+/// Load a string ending with '\0' from user space, return None if it runs into
+/// an inaccessible page.
+pub fn translated_str(token: usize, ptr: *const u8) -> Option<String> {
+    let page_table = PageTable::from_token(token);
+    let mut string = String::new();
+    let mut va = ptr as usize;
+    loop {
+        if !prepare_user_page(&page_table, VirtAddr::from(va), MapPermission::R) {
+            return None;
+        }
+        let ch: u8 = *(page_table
+            .translate_va(VirtAddr::from(va))?
+            .get_mut());
+        if ch == 0 {
+            break;
+        }
+        string.push(ch as char);
+        va += 1;
+    }
+    Some(string)
+}
+
+/// Return None if `ptr` is not writable by the user.
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> Option<&'static mut T> {
+    let page_table = PageTable::from_token(token);
+    let va = ptr as usize;
+    if !prepare_user_page(&page_table, VirtAddr::from(va), MapPermission::W) {
+        return None;
+    }
+    page_table
+        .translate_va(VirtAddr::from(va))
+        .map(|pa| pa.get_mut())
+}
+
+/// A user space buffer, possibly split across several physical pages.
+///
+/// The frames are pinned while the buffer is alive, since a file may block on it
+/// and other tasks could swap its pages out meanwhile.
+pub struct UserBuffer {
+    pub buffers: Vec<&'static mut [u8]>,
+    frames: Vec<FrameTracker>,
+}
+
+impl UserBuffer {
+    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
+        let frames = buffers
+            .iter()
+            .map(|b| frame_share(PhysAddr::from(b.as_ptr() as usize).floor()))
+            .collect();
+        Self { buffers, frames }
+    }
+    pub fn len(&self) -> usize {
+        self.buffers.iter().map(|b| b.len()).sum()
+    }
+}
+
+impl IntoIterator for UserBuffer {
+    type Item = *mut u8;
+    type IntoIter = UserBufferIterator;
+    fn into_iter(self) -> Self::IntoIter {
+        UserBufferIterator {
+            buffers: self.buffers,
+            _frames: self.frames,
+            current_buffer: 0,
+            current_idx: 0,
+        }
+    }
+}
+
+pub struct UserBufferIterator {
+    buffers: Vec<&'static mut [u8]>,
+    _frames: Vec<FrameTracker>,
+    current_buffer: usize,
+    current_idx: usize,
+}
+
+impl Iterator for UserBufferIterator {
+    type Item = *mut u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.current_buffer < self.buffers.len()
+            && self.current_idx >= self.buffers[self.current_buffer].len()
+        {
+            self.current_buffer += 1;
+            self.current_idx = 0;
+        }
+        if self.current_buffer >= self.buffers.len() {
+            None
+        } else {
+            let r = &mut self.buffers[self.current_buffer][self.current_idx] as *mut _;
+            self.current_idx += 1;
+            Some(r)
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::config::PAGE_SIZE;
+use crate::mm::frame_allocator::frame_alloc;
+use crate::mm::swap::UserPage;
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Key of a segment which is never found by other tasks.
+pub const IPC_PRIVATE: usize = 0;
+/// shmctl command to mark a segment for removal.
+pub const IPC_RMID: usize = 0;
+
+bitflags! {
+    pub struct ShmFlags: u32 {
+        const IPC_CREAT = 0o1000;
+        const IPC_EXCL = 0o2000;
+        const SHM_RDONLY = 0o10000;
+    }
+}
+
+/// Shared memory segment, its pages stay resident and are freed
+/// when it is removed and the last attachment is gone.
+struct ShmSegment {
+    key: usize,
+    pages: Vec<Arc<UserPage>>,
+}
+
+pub struct ShmManager {
+    /// segments not removed yet by id
+    segments: BTreeMap<usize, ShmSegment>,
+    next_id: usize,
+}
+
+impl ShmManager {
+    pub fn new() -> Self {
+        Self {
+            segments: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+    fn find_key(&self, key: usize) -> Option<usize> {
+        if key == IPC_PRIVATE {
+            return None;
+        }
+        self.segments
+            .iter()
+            .find(|(_, segment)| segment.key == key)
+            .map(|(id, _)| *id)
+    }
+    fn insert(&mut self, segment: ShmSegment) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.segments.insert(id, segment);
+        id
+    }
+}
+
+lazy_static! {
+    pub static ref SHM_MANAGER: UPSafeCell<ShmManager> = unsafe { UPSafeCell::new(ShmManager::new()) };
+}
+
+/// Find the segment of `key` or create one of `size` bytes, return its id.
+pub fn shm_get(key: usize, size: usize, flags: ShmFlags) -> isize {
+    let page_count = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+    let manager = SHM_MANAGER.exclusive_access();
+    if let Some(id) = manager.find_key(key) {
+        if flags.contains(ShmFlags::IPC_CREAT | ShmFlags::IPC_EXCL) {
+            debug!("shmget failed: key {:#x} exists", key);
+            return -1;
+        }
+        if page_count > manager.segments[&id].pages.len() {
+            debug!("shmget failed: segment of key {:#x} is smaller than {:#x}", key, size);
+            return -1;
+        }
+        return id as isize;
+    }
+    drop(manager);
+    if key != IPC_PRIVATE && !flags.contains(ShmFlags::IPC_CREAT) {
+        debug!("shmget failed: key {:#x} does not exist", key);
+        return -1;
+    }
+    if page_count == 0 {
+        debug!("shmget failed: empty segment");
+        return -1;
+    }
+    // not while holding the manager, the allocation may swap out pages
+    let mut pages = Vec::new();
+    for _ in 0..page_count {
+        match frame_alloc() {
+            Some(frame) => pages.push(UserPage::new(frame)),
+            None => {
+                debug!("shmget failed: out of memory for {:#x} bytes", size);
+                return -1;
+            }
+        }
+    }
+    SHM_MANAGER.exclusive_access().insert(ShmSegment { key, pages }) as isize
+}
+
+/// The pages of a segment to attach.
+pub fn shm_pages(id: usize) -> Option<Vec<Arc<UserPage>>> {
+    SHM_MANAGER
+        .exclusive_access()
+        .segments
+        .get(&id)
+        .map(|segment| segment.pages.clone())
+}
+
+/// Mark a segment for removal, it can not be found or attached any more,
+/// its pages go away with the last attachment.
+pub fn shm_remove(id: usize) -> isize {
+    if SHM_MANAGER.exclusive_access().segments.remove(&id).is_none() {
+        debug!("shmctl failed: segment {} does not exist", id);
+        return -1;
+    }
+    0
+}
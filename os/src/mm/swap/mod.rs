@@ -0,0 +1,221 @@
+use crate::config::{PAGE_SIZE, SWAP_BASE, SWAP_SIZE};
+use crate::mm::address::{PhysPageNum, VirtPageNum};
+use crate::mm::frame_allocator::FrameTracker;
+use crate::mm::page_table::PageTable;
+use crate::sync::UPSafeCell;
+use crate::task::{record_page_event, PageEvent};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::RefMut;
+use easy_fs::{BlockDevice, RamDisk, BLOCK_SZ};
+use lazy_static::*;
+
+mod policy;
+
+use policy::{ReplacePolicy, ResidentPage};
+
+const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_SZ;
+
+/// Where the content of a user page is kept.
+pub enum PageState {
+    Resident(FrameTracker),
+    Swapped(SwapSlot),
+}
+
+/// A page of user space, shared by its area and the swap manager which may evict it.
+pub struct UserPage {
+    state: UPSafeCell<PageState>,
+}
+
+impl UserPage {
+    pub fn new(frame: FrameTracker) -> Arc<Self> {
+        Arc::new(Self {
+            state: unsafe { UPSafeCell::new(PageState::Resident(frame)) },
+        })
+    }
+    pub fn new_swapped(slot: SwapSlot) -> Arc<Self> {
+        Arc::new(Self {
+            state: unsafe { UPSafeCell::new(PageState::Swapped(slot)) },
+        })
+    }
+    pub fn exclusive_access(&self) -> RefMut<'_, PageState> {
+        self.state.exclusive_access()
+    }
+    /// The frame holding the page, None if it is swapped out.
+    pub fn ppn(&self) -> Option<PhysPageNum> {
+        match &*self.exclusive_access() {
+            PageState::Resident(frame) => Some(frame.ppn),
+            PageState::Swapped(_) => None,
+        }
+    }
+}
+
+/// A slot of the swap area, freed when dropped.
+pub struct SwapSlot(usize);
+
+impl SwapSlot {
+    pub fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        SWAP_MANAGER.exclusive_access().dealloc_slot(self.0);
+    }
+}
+
+pub struct SwapManager {
+    device: Arc<dyn BlockDevice>,
+    current: usize, //未使用过的第一个交换槽
+    end: usize,
+    recycled: Vec<usize>,
+    policy: ReplacePolicyImpl,
+    /// drop dead pages from the policy once it has this many
+    cleanup_len: usize,
+}
+
+impl SwapManager {
+    fn new(device: Arc<dyn BlockDevice>, slots: usize) -> Self {
+        Self {
+            device,
+            current: 0,
+            end: slots,
+            recycled: Vec::new(),
+            policy: ReplacePolicyImpl::new(),
+            cleanup_len: 64,
+        }
+    }
+    fn alloc_slot(&mut self) -> Option<SwapSlot> {
+        if let Some(slot) = self.recycled.pop() {
+            Some(SwapSlot(slot))
+        } else if self.current == self.end {
+            None
+        } else {
+            self.current += 1;
+            Some(SwapSlot(self.current - 1))
+        }
+    }
+    fn dealloc_slot(&mut self, slot: usize) {
+        assert!(slot < self.current, "swap slot {} has not been allocated!", slot);
+        assert!(
+            !self.recycled.iter().any(|s| *s == slot),
+            "swap slot {} has been deallocated!",
+            slot
+        );
+        self.recycled.push(slot);
+    }
+    fn write_slot(&self, slot: &SwapSlot, ppn: PhysPageNum) {
+        for (i, block) in ppn.get_bytes_array().chunks(BLOCK_SZ).enumerate() {
+            self.device.write_block(slot.0 * BLOCKS_PER_PAGE + i, block);
+        }
+    }
+    fn read_slot(&self, slot: &SwapSlot, ppn: PhysPageNum) {
+        for (i, block) in ppn.get_bytes_array().chunks_mut(BLOCK_SZ).enumerate() {
+            self.device.read_block(slot.0 * BLOCKS_PER_PAGE + i, block);
+        }
+    }
+    /// Let the page which is mapped at `vpn` of the space `token` be evicted.
+    pub fn register(&mut self, page: &Arc<UserPage>, token: usize, vpn: VirtPageNum) {
+        if self.policy.len() >= self.cleanup_len {
+            self.policy.prune();
+            self.cleanup_len = (self.policy.len() * 2).max(64);
+        }
+        self.policy.insert(ResidentPage::new(page, token, vpn));
+    }
+    /// Evict a page chosen by the policy, return false if there is nothing to evict.
+    pub fn swap_out_one(&mut self) -> bool {
+        let victim = self.policy.pick_victim();
+        // the PTEs of the current space may change, so do the A bits cleared by the policy
+        unsafe {
+            asm!("sfence.vma");
+        }
+        record_page_event(PageEvent::FullTlbFlush);
+        let victim = match victim {
+            Some(victim) => victim,
+            None => return false,
+        };
+        let slot = match self.alloc_slot() {
+            Some(slot) => slot,
+            None => {
+                warn!("SwapManager: swap area is full");
+                self.policy
+                    .insert(ResidentPage::new(&victim.page, victim.token, victim.vpn));
+                return false;
+            }
+        };
+        self.write_slot(&slot, victim.ppn);
+        PageTable::from_token(victim.token).set_swapped(victim.vpn, slot.id());
+        // the frame is freed here
+        *victim.page.exclusive_access() = PageState::Swapped(slot);
+        trace!("SwapManager: swap out vpn={:#x} of token={:#x}", victim.vpn.0, victim.token);
+        unsafe {
+            asm!("sfence.vma");
+        }
+        record_page_event(PageEvent::FullTlbFlush);
+        true
+    }
+}
+
+/// Chosen by exactly one of the swap-* features, swap-clock by default.
+#[cfg(feature = "swap-fifo")]
+type ReplacePolicyImpl = policy::FifoPolicy;
+#[cfg(feature = "swap-clock")]
+type ReplacePolicyImpl = policy::ClockPolicy;
+#[cfg(feature = "swap-enhanced-clock")]
+type ReplacePolicyImpl = policy::EnhancedClockPolicy;
+#[cfg(feature = "swap-wsclock")]
+type ReplacePolicyImpl = policy::WorkingSetPolicy;
+#[cfg(not(any(
+    feature = "swap-fifo",
+    feature = "swap-clock",
+    feature = "swap-enhanced-clock",
+    feature = "swap-wsclock"
+)))]
+compile_error!("choose a page replacement policy with one of the swap-* features");
+
+lazy_static! {
+    pub static ref SWAP_MANAGER: UPSafeCell<SwapManager> = unsafe {
+        UPSafeCell::new(SwapManager::new(
+            Arc::new(RamDisk::from_raw(SWAP_BASE as *mut u8, SWAP_SIZE / BLOCK_SZ)),
+            SWAP_SIZE / PAGE_SIZE,
+        ))
+    };
+}
+
+/// Called when out of frames.
+pub fn swap_out_one() -> bool {
+    let evicted = SWAP_MANAGER.exclusive_access().swap_out_one();
+    if evicted {
+        record_page_event(PageEvent::Eviction);
+    }
+    evicted
+}
+
+/// Load a swapped out page into `frame`, the slot is freed.
+pub fn swap_in(page: &UserPage, frame: FrameTracker) {
+    let ppn = frame.ppn;
+    let slot = match core::mem::replace(&mut *page.exclusive_access(), PageState::Resident(frame)) {
+        PageState::Swapped(slot) => slot,
+        PageState::Resident(_) => panic!("swap in a resident page!"),
+    };
+    SWAP_MANAGER.exclusive_access().read_slot(&slot, ppn);
+    record_page_event(PageEvent::SwapIn);
+}
+
+/// Copy a swapped out page into a new slot, return None if the swap area is full.
+pub fn swap_duplicate(slot: &SwapSlot) -> Option<SwapSlot> {
+    let mut manager = SWAP_MANAGER.exclusive_access();
+    let new_slot = manager.alloc_slot()?;
+    let mut block = [0u8; BLOCK_SZ];
+    for i in 0..BLOCKS_PER_PAGE {
+        manager.device.read_block(slot.0 * BLOCKS_PER_PAGE + i, &mut block);
+        manager.device.write_block(new_slot.0 * BLOCKS_PER_PAGE + i, &block);
+    }
+    Some(new_slot)
+}
+
+/// Called after a page is mapped or swapped in.
+pub fn swap_register(page: &Arc<UserPage>, token: usize, vpn: VirtPageNum) {
+    SWAP_MANAGER.exclusive_access().register(page, token, vpn);
+}
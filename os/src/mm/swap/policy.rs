@@ -0,0 +1,305 @@
+#[cfg(feature = "swap-wsclock")]
+use crate::config::CLOCK_FREQ;
+use crate::mm::address::{PhysPageNum, VirtPageNum};
+use crate::mm::frame_allocator::frame_ref_count;
+#[cfg(any(feature = "swap-clock", feature = "swap-enhanced-clock", feature = "swap-wsclock"))]
+use crate::mm::page_table::{PTEFlags, PageTable};
+use crate::mm::swap::UserPage;
+#[cfg(feature = "swap-wsclock")]
+use crate::timer::get_time;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+/// Pages not accessed for this long leave the working set.
+#[cfg(feature = "swap-wsclock")]
+const WORKING_SET_WINDOW: usize = CLOCK_FREQ / 10; // 100ms
+
+/// Decides which user page to swap out.
+pub trait ReplacePolicy {
+    fn new() -> Self;
+    /// The page is resident and may be evicted from now on.
+    fn insert(&mut self, page: ResidentPage);
+    /// Choose a page to evict and forget it, None if no page can be evicted.
+    fn pick_victim(&mut self) -> Option<Victim>;
+    /// Forget the pages which have been dropped.
+    fn prune(&mut self);
+    fn len(&self) -> usize;
+}
+
+pub struct ResidentPage {
+    page: Weak<UserPage>,
+    token: usize,
+    vpn: VirtPageNum,
+    /// when the page was found accessed last time
+    #[cfg(feature = "swap-wsclock")]
+    last_use: usize,
+}
+
+pub struct Victim {
+    pub page: Arc<UserPage>,
+    pub ppn: PhysPageNum,
+    pub token: usize,
+    pub vpn: VirtPageNum,
+}
+
+enum Candidate {
+    /// unmapped, swapped out or freed
+    Gone,
+    /// the frame is shared, evicting it frees nothing
+    Pinned,
+    Ready(Arc<UserPage>, PhysPageNum),
+}
+
+impl ResidentPage {
+    pub fn new(page: &Arc<UserPage>, token: usize, vpn: VirtPageNum) -> Self {
+        Self {
+            page: Arc::downgrade(page),
+            token,
+            vpn,
+            #[cfg(feature = "swap-wsclock")]
+            last_use: get_time(),
+        }
+    }
+    fn is_alive(&self) -> bool {
+        self.page.strong_count() > 0
+    }
+    fn candidate(&self) -> Candidate {
+        let page = match self.page.upgrade() {
+            Some(page) => page,
+            None => return Candidate::Gone,
+        };
+        match page.ppn() {
+            None => Candidate::Gone,
+            Some(ppn) if frame_ref_count(ppn) > 1 => Candidate::Pinned,
+            Some(ppn) => Candidate::Ready(page, ppn),
+        }
+    }
+    #[cfg(any(feature = "swap-clock", feature = "swap-enhanced-clock", feature = "swap-wsclock"))]
+    fn flags(&self) -> PTEFlags {
+        PageTable::from_token(self.token)
+            .translate(self.vpn)
+            .unwrap()
+            .flags()
+    }
+    #[cfg(any(feature = "swap-clock", feature = "swap-enhanced-clock", feature = "swap-wsclock"))]
+    fn accessed(&self) -> bool {
+        self.flags().contains(PTEFlags::A)
+    }
+    #[cfg(feature = "swap-enhanced-clock")]
+    fn dirty(&self) -> bool {
+        self.flags().contains(PTEFlags::D)
+    }
+    #[cfg(any(feature = "swap-clock", feature = "swap-enhanced-clock", feature = "swap-wsclock"))]
+    fn clear_accessed(&self) {
+        let flags = self.flags();
+        PageTable::from_token(self.token).set_flags(self.vpn, flags - PTEFlags::A);
+    }
+}
+
+/// Visit up to `steps` pages from `hand` in turn, dropping the dead ones on the way,
+/// the first unshared page `choose` agrees on is removed and returned.
+fn scan(
+    pages: &mut Vec<ResidentPage>,
+    hand: &mut usize,
+    steps: usize,
+    mut choose: impl FnMut(&mut ResidentPage) -> bool,
+) -> Option<Victim> {
+    let mut visited = 0;
+    while !pages.is_empty() && visited < steps {
+        if *hand >= pages.len() {
+            *hand = 0;
+        }
+        match pages[*hand].candidate() {
+            Candidate::Gone => {
+                pages.remove(*hand);
+                continue;
+            }
+            Candidate::Pinned => {}
+            Candidate::Ready(page, ppn) => {
+                if choose(&mut pages[*hand]) {
+                    let entry = pages.remove(*hand);
+                    return Some(Victim {
+                        page,
+                        ppn,
+                        token: entry.token,
+                        vpn: entry.vpn,
+                    });
+                }
+            }
+        }
+        visited += 1;
+        *hand += 1;
+    }
+    None
+}
+
+/// Evict the page which became resident first.
+#[cfg(feature = "swap-fifo")]
+pub struct FifoPolicy {
+    pages: Vec<ResidentPage>,
+}
+
+#[cfg(feature = "swap-fifo")]
+impl ReplacePolicy for FifoPolicy {
+    fn new() -> Self {
+        Self { pages: Vec::new() }
+    }
+    fn insert(&mut self, page: ResidentPage) {
+        self.pages.push(page);
+    }
+    fn pick_victim(&mut self) -> Option<Victim> {
+        let steps = self.pages.len();
+        scan(&mut self.pages, &mut 0, steps, |_| true)
+    }
+    fn prune(&mut self) {
+        self.pages.retain(|p| p.is_alive());
+    }
+    fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// Second chance: an accessed page has its A bit cleared and is skipped once.
+#[cfg(feature = "swap-clock")]
+pub struct ClockPolicy {
+    pages: Vec<ResidentPage>,
+    hand: usize,
+}
+
+#[cfg(feature = "swap-clock")]
+impl ReplacePolicy for ClockPolicy {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            hand: 0,
+        }
+    }
+    fn insert(&mut self, page: ResidentPage) {
+        self.pages.push(page);
+    }
+    fn pick_victim(&mut self) -> Option<Victim> {
+        let steps = 2 * self.pages.len();
+        scan(&mut self.pages, &mut self.hand, steps, |p| {
+            if p.accessed() {
+                p.clear_accessed();
+                false
+            } else {
+                true
+            }
+        })
+    }
+    fn prune(&mut self) {
+        self.pages.retain(|p| p.is_alive());
+        self.hand = 0;
+    }
+    fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// Clock which prefers clean pages, by the classes (A, D) in the order
+/// (0, 0), (0, 1), (1, 0), (1, 1).
+#[cfg(feature = "swap-enhanced-clock")]
+pub struct EnhancedClockPolicy {
+    pages: Vec<ResidentPage>,
+    hand: usize,
+}
+
+#[cfg(feature = "swap-enhanced-clock")]
+impl ReplacePolicy for EnhancedClockPolicy {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            hand: 0,
+        }
+    }
+    fn insert(&mut self, page: ResidentPage) {
+        self.pages.push(page);
+    }
+    fn pick_victim(&mut self) -> Option<Victim> {
+        // every A bit is cleared after the first round, so the second one always ends it
+        for _ in 0..2 {
+            let steps = self.pages.len();
+            // look for (0, 0) without touching anything
+            let victim = scan(&mut self.pages, &mut self.hand, steps, |p| {
+                !p.accessed() && !p.dirty()
+            });
+            if victim.is_some() {
+                return victim;
+            }
+            // take (0, 1), clearing A of the pages passed over
+            let steps = self.pages.len();
+            let victim = scan(&mut self.pages, &mut self.hand, steps, |p| {
+                if p.accessed() {
+                    p.clear_accessed();
+                    false
+                } else {
+                    true
+                }
+            });
+            if victim.is_some() {
+                return victim;
+            }
+        }
+        None
+    }
+    fn prune(&mut self) {
+        self.pages.retain(|p| p.is_alive());
+        self.hand = 0;
+    }
+    fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// WSClock: evict a page out of the working set, i.e. not accessed within
+/// `WORKING_SET_WINDOW`, or the least recently used one if all of them are in.
+#[cfg(feature = "swap-wsclock")]
+pub struct WorkingSetPolicy {
+    pages: Vec<ResidentPage>,
+    hand: usize,
+}
+
+#[cfg(feature = "swap-wsclock")]
+impl ReplacePolicy for WorkingSetPolicy {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            hand: 0,
+        }
+    }
+    fn insert(&mut self, page: ResidentPage) {
+        self.pages.push(page);
+    }
+    fn pick_victim(&mut self) -> Option<Victim> {
+        let now = get_time();
+        let steps = self.pages.len();
+        let victim = scan(&mut self.pages, &mut self.hand, steps, |p| {
+            if p.accessed() {
+                p.clear_accessed();
+                p.last_use = now;
+                false
+            } else {
+                now - p.last_use > WORKING_SET_WINDOW
+            }
+        });
+        if victim.is_some() {
+            return victim;
+        }
+        let oldest = self
+            .pages
+            .iter()
+            .filter(|p| matches!(p.candidate(), Candidate::Ready(..)))
+            .map(|p| p.last_use)
+            .min()?;
+        let steps = self.pages.len();
+        scan(&mut self.pages, &mut self.hand, steps, |p| p.last_use == oldest)
+    }
+    fn prune(&mut self) {
+        self.pages.retain(|p| p.is_alive());
+        self.hand = 0;
+    }
+    fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
@@ -1,5 +1,6 @@
 const SBI_SET_TIMER: usize = 0;
 const SBI_CONSOLE_PUTCHAR: usize = 1;
+const SBI_CONSOLE_GETCHAR: usize = 2;
 const SBI_SHUTDOWN: usize = 8;
 
 #[inline(always)]
@@ -20,7 +21,14 @@ pub fn console_putchar(c: usize) {
     sbi_call(SBI_CONSOLE_PUTCHAR, c, 0, 0);
 }
 
+/// Return 0 or usize::MAX(-1) when there is no input, depends on the SBI implementation.
+pub fn console_getchar() -> usize {
+    sbi_call(SBI_CONSOLE_GETCHAR, 0, 0, 0)
+}
+
 pub fn shutdown() -> ! {
+    // flush the filesystem before power off
+    easy_fs::block_cache_sync_all();
     sbi_call(SBI_SHUTDOWN, 0, 0, 0);
     panic!("It should shutdown!");
 }
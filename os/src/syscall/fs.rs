@@ -1,22 +1,168 @@
-use crate::mm::translated_byte_buffer;
-use crate::task::{current_user_token};
+use crate::fs::{make_pipe, open_file, OpenFlags};
+use crate::mm::memory_set::MapPermission;
+use crate::mm::{translated_byte_buffer, translated_refmut, translated_str, UserBuffer};
+use crate::task::{current_task, current_user_token};
 
-const FD_STDOUT: usize = 1;
+/// Upper bound of fds a task may ask for by number.
+const MAX_FD: usize = 1024;
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     trace!("trigger sys_write(fd:{}, buf:{:?}, len:{})", fd, buf, len);
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        debug!("sys_write: fd {} out of fd table", fd);
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        if !file.writable() {
+            debug!("sys_write: fd {} is not writable", fd);
+            return -1;
+        }
+        let file = file.clone();
+        // release the task so that the file may block and switch away
+        drop(inner);
+        match translated_byte_buffer(token, buf, len, MapPermission::R) {
+            Some(buffers) => file.write(UserBuffer::new(buffers)) as isize,
+            None => {
+                debug!("sys_write: buffer {:?} is not accessible", buf);
+                -1
+            }
+        }
+    } else {
+        debug!("sys_write: fd {} is not opened", fd);
+        -1
+    }
+}
 
-    match fd {
-        FD_STDOUT => {
-            let buffers = translated_byte_buffer(current_user_token(), buf, len);
-
-            for buffer in buffers {
-                print!("{}", core::str::from_utf8(buffer).unwrap());
+pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
+    trace!("trigger sys_read(fd:{}, buf:{:?}, len:{})", fd, buf, len);
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        debug!("sys_read: fd {} out of fd table", fd);
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        if !file.readable() {
+            debug!("sys_read: fd {} is not readable", fd);
+            return -1;
+        }
+        let file = file.clone();
+        // release the task so that the file may block and switch away
+        drop(inner);
+        match translated_byte_buffer(token, buf, len, MapPermission::W) {
+            Some(buffers) => file.read(UserBuffer::new(buffers)) as isize,
+            None => {
+                debug!("sys_read: buffer {:?} is not accessible", buf);
+                -1
             }
-            len as isize
         }
+    } else {
+        debug!("sys_read: fd {} is not opened", fd);
+        -1
+    }
+}
+
+pub fn sys_open(path: *const u8, flags: u32) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => {
+            debug!("sys_open: path {:?} is not accessible", path);
+            return -1;
+        }
+    };
+    trace!("trigger sys_open(path:{}, flags:{:#x})", path, flags);
+    let flags = match OpenFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => {
+            debug!("sys_open: unknown flags {:#x}", flags);
+            return -1;
+        }
+    };
+    if let Some(inode) = open_file(path.as_str(), flags) {
+        let task = current_task().unwrap();
+        let mut inner = task.inner_exclusive_access();
+        let fd = inner.alloc_fd();
+        inner.fd_table[fd] = Some(inode);
+        fd as isize
+    } else {
+        debug!("sys_open: cannot open {}", path);
+        -1
+    }
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    trace!("trigger sys_close(fd:{})", fd);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || inner.fd_table[fd].is_none() {
+        debug!("sys_close: fd {} is not opened", fd);
+        return -1;
+    }
+    inner.fd_table[fd].take();
+    0
+}
+
+pub fn sys_pipe(pipe: *mut usize) -> isize {
+    trace!("trigger sys_pipe(pipe:{:?})", pipe);
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    // before borrowing the task, writing user memory may resolve a page fault of it
+    let (read_fd_ref, write_fd_ref) = match (
+        translated_refmut(token, pipe),
+        translated_refmut(token, unsafe { pipe.add(1) }),
+    ) {
+        (Some(read_fd_ref), Some(write_fd_ref)) => (read_fd_ref, write_fd_ref),
         _ => {
-            panic!("Unsupported fd in sys_write!");
+            debug!("sys_pipe: {:?} is not writable", pipe);
+            return -1;
         }
+    };
+    let mut inner = task.inner_exclusive_access();
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = inner.alloc_fd();
+    inner.fd_table[read_fd] = Some(pipe_read);
+    let write_fd = inner.alloc_fd();
+    inner.fd_table[write_fd] = Some(pipe_write);
+    *read_fd_ref = read_fd;
+    *write_fd_ref = write_fd;
+    0
+}
+
+/// Duplicate `fd` into the lowest free fd, both refer to the same file.
+pub fn sys_dup(fd: usize) -> isize {
+    trace!("trigger sys_dup(fd:{})", fd);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || inner.fd_table[fd].is_none() {
+        debug!("sys_dup: fd {} is not opened", fd);
+        return -1;
+    }
+    let new_fd = inner.alloc_fd();
+    inner.fd_table[new_fd] = inner.fd_table[fd].clone();
+    new_fd as isize
+}
+
+/// Make `new_fd` refer to the file of `old_fd`, closing what `new_fd` referred to before.
+pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
+    trace!("trigger sys_dup2(old_fd:{}, new_fd:{})", old_fd, new_fd);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if old_fd >= inner.fd_table.len() || inner.fd_table[old_fd].is_none() {
+        debug!("sys_dup2: fd {} is not opened", old_fd);
+        return -1;
+    }
+    if new_fd >= MAX_FD {
+        debug!("sys_dup2: fd {} is too large", new_fd);
+        return -1;
+    }
+    if new_fd >= inner.fd_table.len() {
+        inner.fd_table.resize(new_fd + 1, None);
     }
+    inner.fd_table[new_fd] = inner.fd_table[old_fd].clone();
+    new_fd as isize
 }
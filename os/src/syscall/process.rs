@@ -1,10 +1,14 @@
 use crate::config::{CLOCK_FREQ, MSEC_PER_SEC};
-use crate::task::{current_mmap, current_munmap, current_sleep_for_ticks, exit_current_and_run_next, set_current_task_priority, suspend_current_and_run_next};
+use crate::task::{add_task, current_mmap, current_mprotect, current_msync, current_munmap, current_sbrk, current_shmat, current_shmdt, current_sleep_for_ticks, current_task, current_user_token, exit_current_and_run_next, set_current_task_priority, suspend_current_and_run_next};
+use crate::fs::read_app;
+use crate::mm::{translated_refmut, translated_str};
+use crate::mm::shm::{shm_get, shm_remove, ShmFlags, IPC_RMID};
+use alloc::sync::Arc;
 use crate::timer::get_time_ms;
 
 pub fn sys_exit(exit_code: i32) -> ! {
     info!("Application exited with code {}", exit_code);
-    exit_current_and_run_next();
+    exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
@@ -31,10 +35,129 @@ pub fn sys_sleep(milliseconds: usize) -> isize {
     0
 }
 
-pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
-    current_mmap(start, len, prot)
+pub fn sys_mmap(start: usize, len: usize, prot: usize, flags: usize, fd: usize, offset: usize) -> isize {
+    current_mmap(start, len, prot, flags, fd, offset)
 }
 
 pub fn sys_munmap(start: usize, len: usize) -> isize {
     current_munmap(start, len)
-}
\ No newline at end of file
+}
+
+/// Grow or shrink the heap by `size` bytes, return the old program break.
+pub fn sys_sbrk(size: isize) -> isize {
+    current_sbrk(size)
+}
+
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    current_mprotect(start, len, prot)
+}
+
+pub fn sys_msync(start: usize, len: usize) -> isize {
+    current_msync(start, len)
+}
+
+/// Get the shared memory segment of `key`, return its id.
+pub fn sys_shmget(key: usize, size: usize, flags: usize) -> isize {
+    // the low 9 bits are the access mode, which is not checked
+    let flags = flags & !0o777;
+    if flags & !(ShmFlags::all().bits() as usize) != 0 {
+        debug!("shmget failed: unrecognized flags={:#x}", flags);
+        return -1;
+    }
+    shm_get(key, size, ShmFlags::from_bits_truncate(flags as u32))
+}
+
+pub fn sys_shmat(id: usize, start: usize, flags: usize) -> isize {
+    current_shmat(id, start, flags)
+}
+
+pub fn sys_shmdt(start: usize) -> isize {
+    current_shmdt(start)
+}
+
+/// Only IPC_RMID is supported.
+pub fn sys_shmctl(id: usize, cmd: usize, _buf: usize) -> isize {
+    if cmd != IPC_RMID {
+        debug!("shmctl failed: unsupported cmd={}", cmd);
+        return -1;
+    }
+    shm_remove(id)
+}
+pub fn sys_getpid() -> isize {
+    current_task().unwrap().getpid() as isize
+}
+
+pub fn sys_fork() -> isize {
+    let current_task = current_task().unwrap();
+    let new_task = match current_task.fork() {
+        Some(new_task) => new_task,
+        None => return -1,
+    };
+    let new_pid = new_task.pid.0;
+    // modify trap context of new_task, because it returns immediately after switching
+    let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
+    // we do not have to move to next instruction since we have done it before
+    // for child process, fork returns 0
+    trap_cx.x[10] = 0;
+    // add new task to scheduler
+    add_task(new_task);
+    new_pid as isize
+}
+
+pub fn sys_exec(path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => {
+            debug!("exec failed: path {:?} is not accessible", path);
+            return -1;
+        }
+    };
+    if let Some(elf_data) = read_app(path.as_str()) {
+        let task = current_task().unwrap();
+        task.exec(elf_data.as_slice(), path.as_str());
+        0
+    } else {
+        debug!("exec failed: app {} not found", path);
+        -1
+    }
+}
+
+/// If there is not a child process whose pid is same as given, return -1.
+/// Else if there is a child process but it is still running, return -2.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+    let task = current_task().unwrap();
+    // find a child process
+    let inner = task.inner_exclusive_access();
+    if !inner
+        .children
+        .iter()
+        .any(|p| pid == -1 || pid as usize == p.getpid())
+    {
+        return -1;
+    }
+    let pair = inner.children.iter().enumerate().find(|(_, p)| {
+        p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
+    });
+    if let Some((idx, _)) = pair {
+        let token = inner.memory_set.token();
+        // writing user memory may resolve a page fault of this task,
+        // the child is kept if the exit code can not be stored
+        drop(inner);
+        let exit_code_ref = match translated_refmut(token, exit_code_ptr) {
+            Some(exit_code_ref) => exit_code_ref,
+            None => {
+                debug!("waitpid failed: {:?} is not writable", exit_code_ptr);
+                return -1;
+            }
+        };
+        let child = task.inner_exclusive_access().children.remove(idx);
+        // confirm that child will be deallocated after being removed from children list
+        assert_eq!(Arc::strong_count(&child), 1);
+        let found_pid = child.getpid();
+        *exit_code_ref = child.inner_exclusive_access().exit_code;
+        found_pid as isize
+    } else {
+        -2
+    }
+}
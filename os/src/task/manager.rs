@@ -0,0 +1,58 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+use crate::sync::UPSafeCell;
+use crate::timer::get_time;
+use super::task::TaskControlBlock;
+
+pub struct TaskManager {
+    ready_queue: Vec<Arc<TaskControlBlock>>,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self {
+            ready_queue: Vec::new(),
+        }
+    }
+    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push(task);
+    }
+    /// Take out the awake task with the smallest stride.
+    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let current_time = get_time();
+        let queue = &self.ready_queue;
+        (0..queue.len())
+            .filter(|id| queue[*id].inner_exclusive_access().task_awake_time < current_time)
+            .reduce(|left, right| {
+                if queue[left].inner_exclusive_access().task_stride
+                    > queue[right].inner_exclusive_access().task_stride
+                {
+                    right
+                } else {
+                    left
+                }
+            })
+            .map(|id| self.ready_queue.remove(id))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ready_queue.is_empty()
+    }
+}
+
+lazy_static! {
+    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
+        unsafe { UPSafeCell::new(TaskManager::new()) };
+}
+
+pub fn add_task(task: Arc<TaskControlBlock>) {
+    TASK_MANAGER.exclusive_access().add(task);
+}
+
+pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
+    TASK_MANAGER.exclusive_access().fetch()
+}
+
+pub fn has_ready_task() -> bool {
+    !TASK_MANAGER.exclusive_access().is_empty()
+}
@@ -1,17 +1,24 @@
 mod context;
+mod manager;
+mod pid;
+mod processor;
 mod switch;
 mod task;
 
-use alloc::vec::Vec;
-use crate::config::{CLOCK_FREQ, MAX_APP_LIFETIME_CLOCK, MSEC_PER_SEC};
-use crate::loader::{get_num_app, get_app_data, get_app_name};
+use alloc::sync::Arc;
+use manager::has_ready_task;
+use crate::config::{CLOCK_FREQ, MAX_APP_LIFETIME_CLOCK, MMAP_TOP, MSEC_PER_SEC, PAGE_SIZE};
+use crate::fs::read_app;
+use crate::mm::address::VirtAddr;
+use crate::mm::memory_set::MapPermission;
+use crate::mm::shm::{shm_pages, ShmFlags};
 use crate::timer::get_time;
 use lazy_static::*;
-use switch::__switch;
-use task::{TaskControlBlock, TaskStatus, BIG_STRIDE};
-use crate::mm::memory_set::MapPermission;
-use crate::sync::UPSafeCell;
-use crate::trap::TrapContext;
+use task::{TaskControlBlock, TaskStatus};
+
+pub use context::TaskContext;
+pub use manager::add_task;
+pub use processor::{current_task, current_trap_cx, current_user_satp, current_user_token, run_tasks, schedule, take_current_task};
 
 bitflags! {
     struct MapProt: u8 {
@@ -21,267 +28,411 @@ bitflags! {
     }
 }
 
+bitflags! {
+    struct MapFlags: u32 {
+        const SHARED = 1 << 0;
+        const PRIVATE = 1 << 1;
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
 impl From<MapProt> for MapPermission {
     fn from(p: MapProt) -> Self {
         MapPermission::from_bits_truncate(p.bits << 1)
     }
 }
 
-pub struct TaskManager {
-    num_app: usize,
-    inner: UPSafeCell<TaskManagerInner>,
-}
-
-struct TaskManagerInner {
-    tasks: Vec<TaskControlBlock>,
-    current_task: usize,
-}
-
-unsafe impl Sync for TaskManager {}
+const INITPROC_NAME: &str = "initproc";
 
 lazy_static! {
-    pub static ref TASK_MANAGER: TaskManager = {
-        info!("init TASK_MANAGER");
-        let num_app = get_num_app();
-        info!("num_app = {}", num_app);
-        let mut tasks: Vec<TaskControlBlock> = Vec::new();
-        for i in 0..num_app {
-            tasks.push(TaskControlBlock::new(
-                get_app_data(i),
-                i,
-                get_app_name(i),
-            ));
-        }
-        TaskManager {
-            num_app,
-            inner: unsafe {UPSafeCell::new(TaskManagerInner {
-                tasks,
-                current_task: 0,
-            })},
-        }
+    /// Root of the task tree, it adopts the orphans and reaps them.
+    pub static ref INITPROC: Arc<TaskControlBlock> = {
+        let elf_data = read_app(INITPROC_NAME).expect("initproc not found!");
+        Arc::new(TaskControlBlock::new(elf_data.as_slice(), INITPROC_NAME))
     };
 }
 
-fn mark_current_suspended() {
-    TASK_MANAGER.mark_current_suspended();
+pub fn add_initproc() {
+    add_task(INITPROC.clone());
 }
 
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
-}
-
-fn run_next_task() {
-    TASK_MANAGER.run_next_task();
-}
-
-pub fn run_first_task() {
-    TASK_MANAGER.run_first_task();
+pub fn suspend_current_and_run_next() {
+    // There must be an application running.
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.update_elapse_time();
+    if task_inner.task_elapse_time > MAX_APP_LIFETIME_CLOCK {
+        warn!(
+            "Force stop the long lifetime app({}) which maybe dead loop",
+            task_inner.task_name
+        );
+        drop(task_inner);
+        drop(task);
+        exit_current_and_run_next(-1);
+        return;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    drop(task_inner);
+    drop(task);
+    // push back to ready queue.
+    add_task(take_current_task().unwrap());
+    // jump to scheduling cycle
+    schedule(task_cx_ptr);
 }
 
-impl TaskManager {
-    fn mark_current_suspended(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_elapse_time +=
-            get_time() - inner.tasks[current].task_last_switch_time;
-        if inner.tasks[current].task_elapse_time > MAX_APP_LIFETIME_CLOCK {
-            inner.tasks[current].task_status = TaskStatus::Exited;
-            warn!(
-                "Force stop the long lifetime app({}) which maybe dead loop",
-                inner.tasks[current].task_name
-            );
-            info!(
-                "{} executed for {}ms",
-                inner.tasks[current].task_name,
-                inner.tasks[current].task_elapse_time / (CLOCK_FREQ / MSEC_PER_SEC)
-            );
-        } else {
-            inner.tasks[current].task_status = TaskStatus::Ready;
+pub fn exit_current_and_run_next(exit_code: i32) {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.update_elapse_time();
+    // Change status to Zombie, it will be reaped by its parent in sys_waitpid
+    inner.task_status = TaskStatus::Zombie;
+    // Record exit code
+    inner.exit_code = exit_code;
+    let page_stats = task.page_stats.exclusive_access();
+    info!(
+        "{} executed for {}ms, {} page faults, {} evictions, {} swap-ins, {} full TLB flushes, {} ASID-tagged switches",
+        inner.task_name,
+        inner.task_elapse_time / (CLOCK_FREQ / MSEC_PER_SEC),
+        page_stats.page_faults,
+        page_stats.evictions,
+        page_stats.swap_ins,
+        page_stats.full_tlb_flushes,
+        page_stats.tagged_switches
+    );
+    drop(page_stats);
+    // move all its children to the initproc
+    if !Arc::ptr_eq(&task, &INITPROC) {
+        let mut initproc_inner = INITPROC.inner_exclusive_access();
+        for child in inner.children.iter() {
+            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+            initproc_inner.children.push(child.clone());
         }
     }
-
-    fn mark_current_exited(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_elapse_time +=
-            get_time() - inner.tasks[current].task_last_switch_time;
-        inner.tasks[current].task_status = TaskStatus::Exited;
-        info!(
-            "{} executed for {}ms",
-            inner.tasks[current].task_name,
-            inner.tasks[current].task_elapse_time / (CLOCK_FREQ / MSEC_PER_SEC)
-        );
-    }
-
-    fn find_next_task(&self) -> Option<usize> {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        (current + 1..current + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .filter(|id| {
-                let current_time = get_time();
-                inner.tasks[*id].task_status == TaskStatus::Ready
-                    && inner.tasks[*id].task_awake_time < current_time
-            })
-            .reduce(|left, right| {
-                if inner.tasks[left].task_stride > inner.tasks[right].task_stride {
-                    right
-                } else {
-                    left
-                }
-            })
+    inner.children.clear();
+    // deallocate user space early, the page table and kernel stack go away when it is reaped
+    inner.memory_set.recycle_data_pages();
+    // close all files
+    inner.fd_table.clear();
+    let task_cx_ptr = &mut inner.task_cx as *mut TaskContext;
+    drop(inner);
+    // the last task is leaving, make sure everything reaches the disk
+    if !has_ready_task() {
+        easy_fs::block_cache_sync_all();
     }
+    // the processor still holds the task until we leave its kernel stack
+    drop(task);
+    schedule(task_cx_ptr);
+}
 
-    fn run_next_task(&self) {
-        if let Some(next) = self.find_next_task() {
-            let mut inner = self.inner.exclusive_access();
-            let current = inner.current_task;
-
-            inner.tasks[next].task_status = TaskStatus::Running;
-            inner.tasks[next].task_stride.value += BIG_STRIDE / inner.tasks[next].task_priority;
-            inner.tasks[next].task_last_switch_time = get_time();
-            inner.current_task = next;
+pub fn set_current_task_priority(prio: u16) {
+    current_task().unwrap().inner_exclusive_access().task_priority = prio;
+}
 
-            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
-            trace!(
-                "run next task {} with stride={}",
-                inner.tasks[next].task_name,
-                inner.tasks[next].task_stride.value,
-            );
-            drop(inner);
-            unsafe {
-                __switch(current_task_cx_ptr, next_task_cx_ptr);
-            }
-        } else {
-            panic!("All applications completed!");
-        }
-    }
+pub fn current_sleep_for_ticks(ticks: usize) {
+    current_task().unwrap().inner_exclusive_access().task_awake_time = get_time() + ticks;
+    suspend_current_and_run_next();
+}
 
-    fn run_first_task(&self) {
-        let mut inner = self.inner.exclusive_access();
-        inner.tasks[0].task_status = TaskStatus::Running;
-        inner.tasks[0].task_last_switch_time = get_time();
-        let next_task_cx_ptr = &inner.tasks[0].task_cx as *const TaskContext;
-        let next_task_name = inner.tasks[0].task_name;
-        core::mem::drop(inner);
-        let mut _unused = TaskContext::zero_init();
-        debug!("run first task {}", next_task_name);
-        unsafe {
-            __switch(&mut _unused as *mut _, next_task_cx_ptr);
-        }
+/// Map `len` bytes of the file `fd` from `offset`, or zeros with MAP_ANONYMOUS.
+/// Return `len` rounded up to pages, or the address the kernel chose if `start` is 0.
+pub fn current_mmap(start: usize, len: usize, prot: usize, flags: usize, fd: usize, offset: usize) -> isize {
+    if len == 0 {
+        debug!("mmap failed: empty len");
+        return -1;
     }
-
-    #[allow(unused)]
-    fn get_current_app(&self) -> usize {
-        self.inner.exclusive_access().current_task
+    if VirtAddr::from(start).page_offset() != 0 {
+        debug!("mmap failed: unaligned vpn with start va={:#x}", start);
+        return -1;
     }
-
-    fn update_current_task_priority(&self, prio: u16) {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_priority = prio;
+    if prot > MapProt::all().bits as usize {
+        debug!("mmap failed: unrecognized prot={:#x}", prot);
+        return -1;
     }
-
-    fn current_sleep_for_ticks(&self, ticks: usize) {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_awake_time = get_time() + ticks;
+    let perm = MapProt::from_bits(prot as u8).unwrap();
+    if perm.is_empty() {
+        debug!("mmap failed: empty prot={:#x}", prot);
+        return -1;
     }
-
-    fn get_current_token(&self) -> usize {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].get_user_token()
+    if flags > MapFlags::all().bits as usize {
+        debug!("mmap failed: unrecognized flags={:#x}", flags);
+        return -1;
     }
-
-    fn get_current_trap_cx(&self) -> &mut TrapContext {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].get_trap_cx()
+    let flags = MapFlags::from_bits(flags as u32).unwrap();
+    if flags.contains(MapFlags::SHARED) == flags.contains(MapFlags::PRIVATE) {
+        debug!("mmap failed: exactly one of MAP_SHARED and MAP_PRIVATE is needed, flags={:#x}", flags.bits);
+        return -1;
     }
-
-    fn do_current_mmap(&self, start: usize, len: usize, prot: usize) -> isize {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        if VirtAddr::from(start).page_offset() != 0 {
-            debug!("mmap failed: unaligned vpn with start va={:#x}", start);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let mut max_permission = MapPermission::all();
+    let file = if flags.contains(MapFlags::ANONYMOUS) {
+        None
+    } else {
+        if offset % PAGE_SIZE != 0 {
+            debug!("mmap failed: unaligned offset={:#x}", offset);
             return -1;
         }
-        if prot > MapProt::all().bits as usize {
-            debug!("mmap failed: unrecognized prot={:#x}", prot);
+        let file = match inner.fd_table.get(fd) {
+            Some(Some(file)) => file.clone(),
+            _ => {
+                debug!("mmap failed: fd {} is not opened", fd);
+                return -1;
+            }
+        };
+        let inode = match file.inode() {
+            Some(inode) => inode,
+            None => {
+                debug!("mmap failed: fd {} is not a regular file", fd);
+                return -1;
+            }
+        };
+        // changes of a private mapping never reach the file
+        if !file.readable()
+            || (flags.contains(MapFlags::SHARED) && perm.contains(MapProt::W) && !file.writable())
+        {
+            debug!("mmap failed: fd {} is not opened for prot={:#x}", fd, prot);
             return -1;
         }
-        let perm = MapProt::from_bits(prot as u8).unwrap();
-        if perm.is_empty() {
-            debug!("mmap failed: empty prot={:#x}", prot);
-            return -1;
+        // nor may mprotect make it writable later
+        if flags.contains(MapFlags::SHARED) && !file.writable() {
+            max_permission.remove(MapPermission::W);
         }
-        inner.tasks[current].memory_set.insert_framed_area(start.into(), (start + len).into(), MapPermission::from(perm) | MapPermission::U)
+        Some((inode, offset))
+    };
+    let start_va = if start == 0 {
+        match inner.memory_set.find_free_area(len) {
+            Some(start_va) => start_va,
+            None => {
+                debug!("mmap failed: no room for len={:#x}", len);
+                return -1;
+            }
+        }
+    } else if inner.memory_set.is_user_range_allowed(start, len) {
+        VirtAddr::from(start)
+    } else {
+        debug!("mmap failed: start va={:#x} with len={:#x} is out of reach", start, len);
+        return -1;
+    };
+    let mapped_len = inner.memory_set.insert_mmap_area(
+        start_va,
+        (start_va.0 + len).into(),
+        MapPermission::from(perm) | MapPermission::U,
+        max_permission,
+        flags.contains(MapFlags::SHARED),
+        file,
+    );
+    if start == 0 && mapped_len >= 0 {
+        start_va.0 as isize
+    } else {
+        mapped_len
     }
+}
 
-    fn do_current_munmap(&self, start: usize, len: usize) -> isize {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        if VirtAddr::from(start).page_offset() != 0 {
-            debug!("munmap failed: unaligned vpn with start va={:#x}", start);
+/// Attach the shared memory segment `id` at `start`, or where the kernel chooses if it is 0,
+/// return the address.
+pub fn current_shmat(id: usize, start: usize, flags: usize) -> isize {
+    if VirtAddr::from(start).page_offset() != 0 {
+        debug!("shmat failed: unaligned vpn with start va={:#x}", start);
+        return -1;
+    }
+    if flags & !(ShmFlags::SHM_RDONLY.bits() as usize) != 0 {
+        debug!("shmat failed: unrecognized flags={:#x}", flags);
+        return -1;
+    }
+    let pages = match shm_pages(id) {
+        Some(pages) => pages,
+        None => {
+            debug!("shmat failed: segment {} does not exist", id);
             return -1;
         }
-        inner.tasks[current].memory_set.remove_frame_area(start.into(), (start + len).into())
+    };
+    let mut permission = MapPermission::R | MapPermission::U;
+    if flags == 0 {
+        permission |= MapPermission::W;
     }
-
-    fn test_current_page_access(&self, address: usize) {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let pte_or_none = inner.tasks[current].memory_set.translate(VirtAddr::from(address).into());
-        if let Some(pte) = pte_or_none {
-            debug!("Task {} access page {:#x}: ppn={:#x}, valid={}, readable={}, writable={}, executable={}",
-                inner.tasks[current].task_name, address, pte.ppn().0, pte.is_valid(), pte.readable(), pte.writable(), pte.executable());
-        } else {
-            debug!("Task {} cannot access page: {:#x}", inner.tasks[current].task_name, address);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let start_va = if start == 0 {
+        match inner.memory_set.find_free_area(pages.len() * PAGE_SIZE) {
+            Some(start_va) => start_va,
+            None => {
+                debug!("shmat failed: no room for segment {}", id);
+                return -1;
+            }
         }
+    } else if inner.memory_set.is_user_range_allowed(start, pages.len() * PAGE_SIZE) {
+        VirtAddr::from(start)
+    } else {
+        debug!("shmat failed: start va={:#x} is out of reach for segment {}", start, id);
+        return -1;
+    };
+    if inner.memory_set.insert_shm_area(start_va, pages, permission) < 0 {
+        return -1;
     }
+    start_va.0 as isize
 }
 
-pub fn current_user_token() -> usize {
-    TASK_MANAGER.get_current_token()
+/// Detach the shared memory segment attached at `start`.
+pub fn current_shmdt(start: usize) -> isize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .remove_shm_area(start.into())
 }
 
-pub fn current_trap_cx() -> &'static mut TrapContext {
-    TASK_MANAGER.get_current_trap_cx()
+/// The end of [start, start + len), None if it wraps or goes above `MMAP_TOP`.
+fn user_range_end(start: usize, len: usize) -> Option<usize> {
+    start.checked_add(len).filter(|&end| end <= MMAP_TOP)
 }
 
-pub fn suspend_current_and_run_next() {
-    mark_current_suspended();
-    run_next_task();
+/// Write the changes of the shared file mappings in the range back to the files.
+pub fn current_msync(start: usize, len: usize) -> isize {
+    if VirtAddr::from(start).page_offset() != 0 {
+        debug!("msync failed: unaligned vpn with start va={:#x}", start);
+        return -1;
+    }
+    let end = match user_range_end(start, len) {
+        Some(end) => end,
+        None => {
+            debug!("msync failed: start va={:#x} with len={:#x} is out of reach", start, len);
+            return -1;
+        }
+    };
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .sync_area(start.into(), end.into())
 }
 
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
-    run_next_task();
+pub fn current_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    if VirtAddr::from(start).page_offset() != 0 {
+        debug!("mprotect failed: unaligned vpn with start va={:#x}", start);
+        return -1;
+    }
+    let end = match user_range_end(start, len) {
+        Some(end) => end,
+        None => {
+            debug!("mprotect failed: start va={:#x} with len={:#x} is out of reach", start, len);
+            return -1;
+        }
+    };
+    if prot > MapProt::all().bits as usize {
+        debug!("mprotect failed: unrecognized prot={:#x}", prot);
+        return -1;
+    }
+    let perm = MapProt::from_bits(prot as u8).unwrap();
+    if perm.is_empty() {
+        debug!("mprotect failed: empty prot={:#x}", prot);
+        return -1;
+    }
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .protect_area(start.into(), end.into(), MapPermission::from(perm) | MapPermission::U)
 }
 
-pub fn set_current_task_priority(prio: u16) {
-    TASK_MANAGER.update_current_task_priority(prio);
+/// Move the program break by `size` bytes, return the old break.
+pub fn current_sbrk(size: isize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let old_brk = inner.program_brk;
+    let new_brk = match (old_brk as isize).checked_add(size) {
+        Some(new_brk) => new_brk as usize,
+        None => {
+            debug!("sbrk failed: break {:#x} moved by {:#x} wraps around", old_brk, size);
+            return -1;
+        }
+    };
+    if new_brk < inner.heap_bottom || new_brk > MMAP_TOP {
+        debug!("sbrk failed: break {:#x} out of the heap from {:#x}", new_brk, inner.heap_bottom);
+        return -1;
+    }
+    let heap_bottom = inner.heap_bottom;
+    if !inner
+        .memory_set
+        .resize_area(heap_bottom.into(), new_brk.into())
+    {
+        return -1;
+    }
+    inner.program_brk = new_brk;
+    old_brk as isize
 }
 
-pub fn current_sleep_for_ticks(ticks: usize) {
-    TASK_MANAGER.current_sleep_for_ticks(ticks);
-    suspend_current_and_run_next();
+pub fn current_munmap(start: usize, len: usize) -> isize {
+    if VirtAddr::from(start).page_offset() != 0 {
+        debug!("munmap failed: unaligned vpn with start va={:#x}", start);
+        return -1;
+    }
+    let end = match user_range_end(start, len) {
+        Some(end) => end,
+        None => {
+            debug!("munmap failed: start va={:#x} with len={:#x} is out of reach", start, len);
+            return -1;
+        }
+    };
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .remove_frame_area(start.into(), end.into())
 }
 
-pub fn current_mmap(start: usize, len: usize, prot: usize) -> isize {
-    TASK_MANAGER.do_current_mmap(start, len, prot)
+/// Try to resolve a page fault of the current task, return false if it should be killed.
+pub fn current_handle_page_fault(address: usize, access: MapPermission) -> bool {
+    record_page_event(PageEvent::Fault);
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .handle_page_fault(VirtAddr::from(address), access)
 }
 
-pub fn current_munmap(start: usize, len: usize) -> isize {
-    TASK_MANAGER.do_current_munmap(start, len)
+/// Whether a page fault at `address` of the current task is caused by a stack overflow.
+pub fn current_is_stack_overflow(address: usize) -> bool {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .is_stack_overflow(VirtAddr::from(address))
 }
 
-pub fn test_translate_in_current(address: usize) {
-    TASK_MANAGER.test_current_page_access(address)
+pub enum PageEvent {
+    Fault,
+    /// a page is swapped out to get a frame for the current task
+    Eviction,
+    SwapIn,
+    /// the whole TLB is flushed
+    FullTlbFlush,
+    /// back to user space without flushing the TLB
+    TaggedSwitch,
 }
 
-pub use context::TaskContext;
-use crate::mm::address::VirtAddr;
+/// Count a paging event of the current task, if there is one.
+pub fn record_page_event(event: PageEvent) {
+    if let Some(task) = current_task() {
+        let mut page_stats = task.page_stats.exclusive_access();
+        match event {
+            PageEvent::Fault => page_stats.page_faults += 1,
+            PageEvent::Eviction => page_stats.evictions += 1,
+            PageEvent::SwapIn => page_stats.swap_ins += 1,
+            PageEvent::FullTlbFlush => page_stats.full_tlb_flushes += 1,
+            PageEvent::TaggedSwitch => page_stats.tagged_switches += 1,
+        }
+    }
+}
 
+pub fn test_translate_in_current(address: usize) {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let pte_or_none = inner.memory_set.translate(VirtAddr::from(address).into());
+    if let Some(pte) = pte_or_none {
+        debug!("Task {} access page {:#x}: ppn={:#x}, valid={}, readable={}, writable={}, executable={}",
+            inner.task_name, address, pte.ppn().0, pte.is_valid(), pte.readable(), pte.writable(), pte.executable());
+    } else {
+        debug!("Task {} cannot access page: {:#x}", inner.task_name, address);
+    }
+}
@@ -0,0 +1,99 @@
+use alloc::sync::Arc;
+use lazy_static::*;
+use crate::sync::UPSafeCell;
+use crate::timer::get_time;
+use crate::trap::TrapContext;
+use super::manager::{fetch_task, has_ready_task};
+use super::switch::__switch;
+use super::task::{TaskControlBlock, TaskStatus, BIG_STRIDE};
+use super::TaskContext;
+
+pub struct Processor {
+    current: Option<Arc<TaskControlBlock>>,
+    idle_task_cx: TaskContext,
+}
+
+impl Processor {
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            idle_task_cx: TaskContext::zero_init(),
+        }
+    }
+    fn get_idle_task_cx_ptr(&mut self) -> *mut TaskContext {
+        &mut self.idle_task_cx as *mut _
+    }
+    pub fn take_current(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.current.take()
+    }
+    pub fn current(&self) -> Option<Arc<TaskControlBlock>> {
+        self.current.as_ref().map(|task| Arc::clone(task))
+    }
+}
+
+lazy_static! {
+    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+}
+
+pub fn run_tasks() {
+    loop {
+        let mut processor = PROCESSOR.exclusive_access();
+        if let Some(task) = fetch_task() {
+            let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
+            let mut task_inner = task.inner_exclusive_access();
+            task_inner.task_status = TaskStatus::Running;
+            task_inner.task_stride.value += BIG_STRIDE / task_inner.task_priority;
+            task_inner.task_last_switch_time = get_time();
+            let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
+            trace!(
+                "run next task {}(pid={}) with stride={}",
+                task_inner.task_name,
+                task.getpid(),
+                task_inner.task_stride.value,
+            );
+            drop(task_inner);
+            processor.current = Some(task);
+            drop(processor);
+            unsafe {
+                __switch(idle_task_cx_ptr, next_task_cx_ptr);
+            }
+            // back on the idle stack: an exited task can be released safely now
+            PROCESSOR.exclusive_access().take_current();
+        } else if !has_ready_task() {
+            panic!("All applications completed!");
+        }
+    }
+}
+
+pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
+    PROCESSOR.exclusive_access().take_current()
+}
+
+pub fn current_task() -> Option<Arc<TaskControlBlock>> {
+    PROCESSOR.exclusive_access().current()
+}
+
+pub fn current_user_token() -> usize {
+    let task = current_task().unwrap();
+    let token = task.inner_exclusive_access().get_user_token();
+    token
+}
+
+/// satp of the current user space to return to, with its ASID.
+pub fn current_user_satp() -> usize {
+    current_task().unwrap().inner_exclusive_access().memory_set.satp()
+}
+
+pub fn current_trap_cx() -> &'static mut TrapContext {
+    current_task().unwrap().inner_exclusive_access().get_trap_cx()
+}
+
+/// Switch from the current task back to the idle control flow in `run_tasks`.
+pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
+    let mut processor = PROCESSOR.exclusive_access();
+    let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
+    drop(processor);
+    unsafe {
+        __switch(switched_task_cx_ptr, idle_task_cx_ptr);
+    }
+}
@@ -1,8 +1,16 @@
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::cell::RefMut;
 use core::cmp::Ordering::{self, Greater, Less};
-use crate::config::{kernel_stack_position, TRAP_CONTEXT};
+use crate::config::TRAP_CONTEXT;
+use crate::fs::{File, Stdin, Stdout};
 use crate::mm::address::{PhysPageNum, VirtAddr};
-use crate::mm::memory_set::{KERNEL_SPACE, MapPermission, MemorySet};
+use crate::mm::memory_set::{KERNEL_SPACE, MemorySet};
+use crate::sync::UPSafeCell;
 use crate::task::TaskContext;
+use crate::task::pid::{pid_alloc, KernelStack, PidHandle};
+use crate::timer::get_time;
 use crate::trap::{trap_handler, TrapContext};
 
 pub const BIG_STRIDE: u16 = 65535; // u16::MAX
@@ -13,7 +21,7 @@ pub enum TaskStatus {
     UnInit,  // 未初始化
     Ready,   // 准备运行
     Running, // 正在运行
-    Exited,  // 已退出
+    Zombie,  // 已退出，等待父进程回收
 }
 
 pub struct Stride {
@@ -45,52 +53,127 @@ impl PartialEq for Stride {
 }
 
 pub struct TaskControlBlock {
+    // immutable
+    pub pid: PidHandle,
+    pub kernel_stack: KernelStack,
+    // mutable
+    pub page_stats: UPSafeCell<PageStats>,
+    inner: UPSafeCell<TaskControlBlockInner>,
+}
+
+/// Paging events of a task, kept out of `inner` since they happen while it is borrowed.
+#[derive(Default)]
+pub struct PageStats {
+    pub page_faults: usize,
+    pub evictions: usize,
+    pub swap_ins: usize,
+    /// flushes of the whole TLB: on returns to user space without ASIDs, ASID rollovers and swap-outs
+    pub full_tlb_flushes: usize,
+    /// returns to user space which kept the TLB thanks to the ASID
+    pub tagged_switches: usize,
+}
+
+pub struct TaskControlBlockInner {
     pub task_cx: TaskContext,
     pub task_status: TaskStatus,
     pub memory_set: MemorySet,
     pub trap_cx_ppn: PhysPageNum,
     pub base_size: usize,
-    pub task_name: &'static str,
+    pub heap_bottom: usize,
+    /// current end of the heap, the heap area ends at the page containing it
+    pub program_brk: usize,
+    pub task_name: String,
     pub task_stride: Stride,
     pub task_priority: u16,
     pub task_awake_time: usize,
     pub task_elapse_time: usize,
     pub task_last_switch_time: usize,
+    pub parent: Option<Weak<TaskControlBlock>>,
+    pub children: Vec<Arc<TaskControlBlock>>,
+    pub exit_code: i32,
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+}
+
+impl TaskControlBlockInner {
+    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
+        self.trap_cx_ppn.get_mut()
+    }
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+    pub fn is_zombie(&self) -> bool {
+        self.task_status == TaskStatus::Zombie
+    }
+    /// Return the lowest free fd.
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+    /// Account the time since the last switch into `task_elapse_time`.
+    pub fn update_elapse_time(&mut self) {
+        let current_time = get_time();
+        self.task_elapse_time += current_time - self.task_last_switch_time;
+        self.task_last_switch_time = current_time;
+    }
 }
 
 impl TaskControlBlock {
-    pub fn new(elf_data: &[u8], app_id: usize, app_name: &'static str) -> Self {
+    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+    pub fn new(elf_data: &[u8], app_name: &str) -> Self {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, user_sp, heap_bottom, entry_point) = MemorySet::from_elf(elf_data);
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
             .ppn();
-        let task_status = TaskStatus::Ready;
-        // map a kernel-stack in kernel space
-        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(app_id);
-        KERNEL_SPACE
-            .exclusive_access()
-            .insert_framed_area(
-                kernel_stack_bottom.into(),
-                kernel_stack_top.into(),
-                MapPermission::R | MapPermission::W,
-            );
+        // alloc a pid and a kernel stack in kernel space
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.get_top();
         let task_control_block = Self {
-            task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-            task_status,
-            memory_set,
-            trap_cx_ppn,
-            base_size: user_sp,
-            task_name: app_name,
-            task_stride: Stride { value: 0 },
-            task_priority: 16,
-            task_awake_time: 0,
-            task_elapse_time: 0,
-            task_last_switch_time: 0,
+            pid: pid_handle,
+            kernel_stack,
+            page_stats: unsafe { UPSafeCell::new(PageStats::default()) },
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    task_status: TaskStatus::Ready,
+                    memory_set,
+                    trap_cx_ppn,
+                    base_size: user_sp,
+                    heap_bottom,
+                    program_brk: heap_bottom,
+                    task_name: String::from(app_name),
+                    task_stride: Stride { value: 0 },
+                    task_priority: 16,
+                    task_awake_time: 0,
+                    task_elapse_time: 0,
+                    task_last_switch_time: 0,
+                    parent: None,
+                    children: Vec::new(),
+                    exit_code: 0,
+                    fd_table: vec![
+                        // 0 -> stdin
+                        Some(Arc::new(Stdin)),
+                        // 1 -> stdout
+                        Some(Arc::new(Stdout)),
+                        // 2 -> stderr
+                        Some(Arc::new(Stdout)),
+                    ],
+                })
+            },
         };
         // prepare TrapContext in user space
-        let trap_cx = task_control_block.get_trap_cx();
+        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
             entry_point,
             user_sp,
@@ -100,10 +183,79 @@ impl TaskControlBlock {
         );
         task_control_block
     }
-    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
-        self.trap_cx_ppn.get_mut()
+    /// Replace the user space with a new elf image, keeping pid, kernel stack and the task tree.
+    pub fn exec(&self, elf_data: &[u8], app_name: &str) {
+        // memory_set with elf program headers/trampoline/trap context/user stack
+        let (memory_set, user_sp, heap_bottom, entry_point) = MemorySet::from_elf(elf_data);
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT).into())
+            .unwrap()
+            .ppn();
+        let mut inner = self.inner_exclusive_access();
+        // substitute memory_set, the old one is recycled here after its shared file pages are written back
+        inner.memory_set.recycle_data_pages();
+        inner.memory_set = memory_set;
+        inner.trap_cx_ppn = trap_cx_ppn;
+        inner.base_size = user_sp;
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        inner.task_name = String::from(app_name);
+        // initialize trap_cx
+        let trap_cx = inner.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            self.kernel_stack.get_top(),
+            trap_handler as usize,
+        );
     }
-    pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
+    /// Return None if the user space can not be duplicated.
+    pub fn fork(self: &Arc<TaskControlBlock>) -> Option<Arc<TaskControlBlock>> {
+        let mut parent_inner = self.inner_exclusive_access();
+        // share user space copy-on-write (include trap context, which is copied)
+        let memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set)?;
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT).into())
+            .unwrap()
+            .ppn();
+        // the child shares the open files of its parent
+        let fd_table = parent_inner.fd_table.clone();
+        // alloc a pid and a kernel stack in kernel space
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.get_top();
+        let task_control_block = Arc::new(TaskControlBlock {
+            pid: pid_handle,
+            kernel_stack,
+            page_stats: unsafe { UPSafeCell::new(PageStats::default()) },
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    task_status: TaskStatus::Ready,
+                    memory_set,
+                    trap_cx_ppn,
+                    base_size: parent_inner.base_size,
+                    heap_bottom: parent_inner.heap_bottom,
+                    program_brk: parent_inner.program_brk,
+                    task_name: parent_inner.task_name.clone(),
+                    task_stride: Stride { value: parent_inner.task_stride.value },
+                    task_priority: parent_inner.task_priority,
+                    task_awake_time: 0,
+                    task_elapse_time: 0,
+                    task_last_switch_time: 0,
+                    parent: Some(Arc::downgrade(self)),
+                    children: Vec::new(),
+                    exit_code: 0,
+                    fd_table,
+                })
+            },
+        });
+        // add child
+        parent_inner.children.push(task_control_block.clone());
+        // modify kernel_sp in trap_cx
+        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
+        trap_cx.kernel_sp = kernel_stack_top;
+        Some(task_control_block)
     }
 }
@@ -1,3 +1,4 @@
+use crate::mm::asid_enabled;
 use riscv::register::sstatus::{self, set_spp, Sstatus, SPP};
 
 #[repr(C)]
@@ -8,6 +9,8 @@ pub struct TrapContext {
     pub kernel_satp: usize,
     pub kernel_sp: usize,
     pub trap_handler: usize,
+    /// 1 if the TLB is not tagged by ASIDs and is flushed on entering the kernel
+    pub flush_tlb: usize,
 }
 
 impl TrapContext {
@@ -32,6 +35,7 @@ impl TrapContext {
             kernel_satp,
             kernel_sp,
             trap_handler,
+            flush_tlb: !asid_enabled() as usize,
         };
         cx.set_sp(sp);
         cx
@@ -6,8 +6,10 @@ use riscv::register::{
     sie, stval, stvec,
 };
 
+use crate::mm::asid_enabled;
+use crate::mm::memory_set::MapPermission;
 use crate::syscall::syscall;
-use crate::task::{current_trap_cx, current_user_token, exit_current_and_run_next, suspend_current_and_run_next, test_translate_in_current};
+use crate::task::{current_handle_page_fault, current_is_stack_overflow, current_trap_cx, current_user_satp, exit_current_and_run_next, record_page_event, PageEvent, suspend_current_and_run_next, test_translate_in_current};
 use crate::timer::set_next_trigger;
 
 global_asm!(include_str!("trap.S"));
@@ -30,28 +32,39 @@ pub fn trap_from_kernel() -> ! {
 #[no_mangle]
 pub fn trap_handler() -> ! {
     set_kernel_trap_entry();
-    let cx = current_trap_cx();
     let scause = scause::read();
     let stval = stval::read();
     trace!("trap: scause={:?}, stval={:#x}", scause.cause(), stval);
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             //println!("start do UserEnvCall");
+            let mut cx = current_trap_cx();
             cx.sepc += 4;
-            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]]);
+            // cx is changed during sys_exec, so we have to call it again
+            cx = current_trap_cx();
+            cx.x[10] = result as usize;
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
             suspend_current_and_run_next();
         }
-        Trap::Exception(Exception::StoreFault) | Trap::Exception(Exception::StorePageFault) | Trap::Exception(Exception::LoadPageFault) => {
-            error!("PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.", stval, cx.sepc);
+        // demand paging or copy-on-write, retry the access
+        Trap::Exception(Exception::StorePageFault) if current_handle_page_fault(stval, MapPermission::W) => {}
+        Trap::Exception(Exception::LoadPageFault) if current_handle_page_fault(stval, MapPermission::R) => {}
+        Trap::Exception(Exception::InstructionPageFault) if current_handle_page_fault(stval, MapPermission::X) => {}
+        Trap::Exception(Exception::StorePageFault) | Trap::Exception(Exception::LoadPageFault) if current_is_stack_overflow(stval) => {
+            error!("Stack overflow in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.", stval, current_trap_cx().sepc);
+            exit_current_and_run_next(-4);
+        }
+        Trap::Exception(Exception::StoreFault) | Trap::Exception(Exception::StorePageFault) | Trap::Exception(Exception::LoadPageFault) | Trap::Exception(Exception::InstructionPageFault) => {
+            error!("PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.", stval, current_trap_cx().sepc);
             test_translate_in_current(stval);
-            exit_current_and_run_next();
+            exit_current_and_run_next(-2);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
             error!("IllegalInstruction in application, core dumped.");
-            exit_current_and_run_next();
+            exit_current_and_run_next(-3);
         }
         _ => {
             panic!(
@@ -76,7 +89,13 @@ pub fn trap_return() -> ! {
     set_user_trap_entry();
 
     let trap_cx_ptr = TRAP_CONTEXT;
-    let user_satp = current_user_token();
+    let user_satp = current_user_satp();
+    let flush_tlb = !asid_enabled();
+    record_page_event(if flush_tlb {
+        PageEvent::FullTlbFlush
+    } else {
+        PageEvent::TaggedSwitch
+    });
     extern "C" {
         fn __alltraps();
         fn __restore();
@@ -88,6 +107,7 @@ pub fn trap_return() -> ! {
             restore_va = in(reg) restore_va,
             in("a0") trap_cx_ptr,
             in("a1") user_satp,
+            in("a2") flush_tlb as usize,
             options(noreturn)
         )
     }
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+#[macro_use]
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use user_lib::sbrk;
+
+/*
+理想结果：用户堆通过 sbrk 按需增长，可以构造较大的 Vec 和 BTreeMap 并得到正确内容，
+最终输出 Test heap OK!
+*/
+
+const VEC_LEN: usize = 256 * 1024;
+const MAP_LEN: usize = 20000;
+
+#[no_mangle]
+fn main() -> i32 {
+    let bottom = sbrk(0);
+    let boxed = Box::new(0x5a5a_usize);
+    assert_eq!(*boxed, 0x5a5a);
+    // 2MiB, far more than one step of heap growth
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..VEC_LEN {
+        v.push(i * 3);
+    }
+    assert!(sbrk(0) - bottom >= (VEC_LEN * 8) as isize);
+    assert!(v.iter().enumerate().all(|(i, x)| *x == i * 3));
+    let mut map: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+    for i in 0..MAP_LEN {
+        map.insert(i * 7 % MAP_LEN, vec![i as u8; i % 16]);
+    }
+    assert_eq!(map.len(), MAP_LEN);
+    for i in 0..MAP_LEN {
+        let value = &map[&(i * 7 % MAP_LEN)];
+        assert!(value.len() == i % 16 && value.iter().all(|x| *x == i as u8));
+    }
+    drop(v);
+    drop(map);
+    // freed memory is reused instead of growing the heap again
+    let brk = sbrk(0);
+    let v: Vec<usize> = vec![1; VEC_LEN / 2];
+    assert_eq!(v.iter().sum::<usize>(), VEC_LEN / 2);
+    assert_eq!(sbrk(0), brk);
+    println!("Test heap OK!");
+    0
+}
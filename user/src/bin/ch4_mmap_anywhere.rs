@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, sbrk};
+
+/*
+理想结果：start 为 0 时内核在空闲区间中选择不重叠的映射地址，释放后的空洞可以被再次选中，
+堆仍能通过 sbrk 增长，长度为 0 的映射失败，
+跳板页等用户不可达的地址不能被映射，最终输出 Test mmap anywhere OK!
+*/
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let prot: usize = 3;
+    let a = mmap(0, 2 * PAGE_SIZE, prot);
+    let b = mmap(0, 3 * PAGE_SIZE, prot);
+    let c = mmap(0, PAGE_SIZE, prot);
+    assert!(a > 0 && b > 0 && c > 0);
+    let (a, b, c) = (a as usize, b as usize, c as usize);
+    // no overlap
+    assert!(b + 3 * PAGE_SIZE <= a || a + 2 * PAGE_SIZE <= b);
+    assert!(c + PAGE_SIZE <= b || b + 3 * PAGE_SIZE <= c);
+    assert!(c + PAGE_SIZE <= a || a + 2 * PAGE_SIZE <= c);
+    for (start, len) in [(a, 2 * PAGE_SIZE), (b, 3 * PAGE_SIZE), (c, PAGE_SIZE)] {
+        for page in (start..start + len).step_by(PAGE_SIZE) {
+            unsafe {
+                *(page as *mut usize) = page;
+            }
+        }
+    }
+    for page in (a..a + 2 * PAGE_SIZE).step_by(PAGE_SIZE) {
+        assert_eq!(unsafe { *(page as *const usize) }, page);
+    }
+    // a chosen range is really taken
+    assert_eq!(mmap(b, PAGE_SIZE, prot), -1);
+    // the hole left by b fits the next one
+    assert_eq!(munmap(b, 3 * PAGE_SIZE), (3 * PAGE_SIZE) as isize);
+    let d = mmap(0, 3 * PAGE_SIZE, prot);
+    assert_eq!(d as usize, b);
+    // the heap keeps growing below the mappings
+    let brk = sbrk(0);
+    assert_eq!(sbrk(16 * PAGE_SIZE as isize), brk);
+    unsafe {
+        *(brk as *mut usize) = 1;
+    }
+    // an empty range maps nothing, wherever it is
+    assert_eq!(mmap(0, 0, prot), -1);
+    assert_eq!(mmap(0x10000000, 0, prot), -1);
+    // the trampoline and ranges wrapping around are out of reach
+    assert_eq!(mmap(usize::MAX - PAGE_SIZE + 1, PAGE_SIZE, prot), -1);
+    assert_eq!(mmap(usize::MAX - PAGE_SIZE * 2 + 1, 4 * PAGE_SIZE, prot), -1);
+    assert_eq!(munmap(0x10000000, usize::MAX - 0x10000000 + 1 + PAGE_SIZE), -1);
+    assert_eq!(munmap(0x10000000, 0x7ffffff000), -1);
+    println!("Test mmap anywhere OK!");
+    0
+}
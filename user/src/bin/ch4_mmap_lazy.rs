@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：映射远大于物理内存的区域也能成功，只有被访问的页面才占用物理页帧，
+部分解除映射后其余页面仍可访问，最终输出 Test lazy mmap OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let page: usize = 4096;
+    // 64MiB, far more than the physical memory
+    let len: usize = 64 * 1024 * 1024;
+    let prot: usize = 3;
+    assert_eq!(mmap(start, len, prot), len as isize);
+    let touched = [start, start + len / 2, start + len - page];
+    for addr in touched.iter() {
+        let ptr = *addr as *mut usize;
+        unsafe {
+            // a fresh page is zeroed
+            assert_eq!(*ptr, 0);
+            *ptr = *addr;
+        }
+    }
+    for addr in touched.iter() {
+        unsafe {
+            assert_eq!(*(*addr as *const usize), *addr);
+        }
+    }
+    // unmap the middle part, both ends stay mapped
+    assert_eq!(munmap(start + page, len - 2 * page), (len - 2 * page) as isize);
+    unsafe {
+        assert_eq!(*(start as *const usize), start);
+        assert_eq!(*((start + len - page) as *const usize), start + len - page);
+    }
+    assert_eq!(munmap(start + page, page), -1);
+    assert_eq!(munmap(start, page), page as isize);
+    assert_eq!(munmap(start + len - page, page), page as isize);
+    println!("Test lazy mmap OK!");
+    0
+}
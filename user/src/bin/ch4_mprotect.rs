@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, mmap, mprotect, munmap, wait};
+
+/*
+理想结果：修改映射区间中间一页的权限后，该页只读、两侧页面仍可写，
+对只读页面写入的子进程被杀死（退出码 -2），未完全映射的区间修改失败，
+最终输出 Test mprotect OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let page: usize = 4096;
+    let len = 3 * page;
+    assert_eq!(mmap(start, len, 3), len as isize);
+    for i in 0..3 {
+        unsafe {
+            *((start + i * page) as *mut usize) = i + 1;
+        }
+    }
+    // the middle page becomes read-only
+    assert_eq!(mprotect(start + page, page, 1), page as isize);
+    unsafe {
+        assert_eq!(*((start + page) as *const usize), 2);
+        *(start as *mut usize) = 4;
+        *((start + 2 * page) as *mut usize) = 6;
+    }
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            *((start + page) as *mut usize) = 5;
+        }
+        println!("Should cause error, Test mprotect fail!");
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    // not fully mapped, nothing changes
+    assert_eq!(mprotect(start + 2 * page, 2 * page, 3), -1);
+    assert_eq!(mprotect(start + page, page, 8), -1);
+    // writable again
+    assert_eq!(mprotect(start, len, 3), len as isize);
+    unsafe {
+        *((start + page) as *mut usize) = 5;
+        assert_eq!(*(start as *const usize), 4);
+        assert_eq!(*((start + page) as *const usize), 5);
+        assert_eq!(*((start + 2 * page) as *const usize), 6);
+    }
+    assert_eq!(munmap(start, len), len as isize);
+    println!("Test mprotect OK!");
+    0
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, sbrk, wait};
+
+/*
+理想结果：堆可以通过 sbrk 增长和收缩，收缩后被释放的页面不可再访问（子进程被杀死，退出码 -2），
+堆不能收缩到起点以下，也不能增长到用户地址空间以外，最终输出 Test sbrk OK!
+*/
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let bottom = sbrk(0);
+    assert!(bottom > 0);
+    assert_eq!(sbrk(4 * PAGE_SIZE as isize), bottom);
+    assert_eq!(sbrk(0), bottom + 4 * PAGE_SIZE as isize);
+    let bottom = bottom as usize;
+    for i in 0..4 {
+        unsafe {
+            *((bottom + i * PAGE_SIZE) as *mut usize) = i + 1;
+        }
+    }
+    // give back the last two pages, the break may stop inside a page
+    assert_eq!(sbrk(-(PAGE_SIZE as isize) * 2 - 8), (bottom + 4 * PAGE_SIZE) as isize);
+    unsafe {
+        assert_eq!(*(bottom as *const usize), 1);
+        assert_eq!(*((bottom + PAGE_SIZE) as *const usize), 2);
+    }
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            *((bottom + 3 * PAGE_SIZE) as *mut usize) = 4;
+        }
+        println!("Should cause error, Test sbrk fail!");
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    assert_eq!(sbrk(-(4 * PAGE_SIZE as isize)), -1);
+    // nor grow beyond the user space
+    assert_eq!(sbrk(isize::MAX), -1);
+    let brk = sbrk(0) as usize;
+    assert_eq!(sbrk(-((brk - bottom) as isize)), brk as isize);
+    assert_eq!(sbrk(0), bottom as isize);
+    println!("Test sbrk OK!");
+    0
+}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::{read_volatile, write_volatile};
+use user_lib::{fork, wait};
+
+/*
+理想结果：递归使用远超初始 8KiB 的栈空间时用户栈自动向下增长，
+无限递归的子进程因栈溢出被杀死（内核输出 Stack overflow，退出码 -4），
+最终输出 Test stack OK!
+*/
+
+const FRAME_SIZE: usize = 1024;
+
+/// Use about `FRAME_SIZE` bytes of stack per level.
+fn recurse(depth: usize) -> usize {
+    let mut frame = [0u8; FRAME_SIZE];
+    unsafe {
+        write_volatile(&mut frame[depth % FRAME_SIZE], depth as u8);
+    }
+    let below = if depth == 0 { 0 } else { recurse(depth - 1) };
+    below + unsafe { read_volatile(&frame[depth % FRAME_SIZE]) } as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // about 256KiB of stack
+    let depth = 256;
+    let expected: usize = (0..=depth).map(|d| d % 256).sum();
+    assert_eq!(recurse(depth), expected);
+    let pid = fork();
+    if pid == 0 {
+        recurse(usize::MAX);
+        println!("Should cause error, Test stack fail!");
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -4);
+    println!("Test stack OK!");
+    0
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, mmap, munmap, wait};
+
+/*
+理想结果：写入超过物理内存大小的页面时内核把部分页面换出而不是崩溃，
+再次访问时页面被换入且内容不变，fork 出的子进程也能读到被换出的页面，
+最终输出 Test swap OK!
+*/
+
+const PAGE_SIZE: usize = 4096;
+// 8MiB, more than all the memory the kernel manages
+const PAGES: usize = 2048;
+
+fn value(page: usize) -> usize {
+    page * 0x9e37 + 1
+}
+
+fn check(start: usize) -> bool {
+    (0..PAGES).all(|page| unsafe {
+        let ptr = (start + page * PAGE_SIZE) as *const usize;
+        *ptr == value(page) && *ptr.add(PAGE_SIZE / 8 - 1) == value(page)
+    })
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len = PAGES * PAGE_SIZE;
+    assert_eq!(mmap(start, len, 3), len as isize);
+    for page in 0..PAGES {
+        let ptr = (start + page * PAGE_SIZE) as *mut usize;
+        unsafe {
+            *ptr = value(page);
+            *ptr.add(PAGE_SIZE / 8 - 1) = value(page);
+        }
+    }
+    assert!(check(start));
+    let pid = fork();
+    if pid == 0 {
+        // the child sees the pages whether they are swapped out or not
+        assert!(check(start));
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert!(check(start));
+    assert_eq!(munmap(start, len), len as isize);
+    println!("Test swap OK!");
+    0
+}
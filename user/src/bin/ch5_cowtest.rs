@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, wait};
+
+/*
+理想结果：fork 后父子进程各自对共享页面的写入互不可见（写时复制），
+子进程退出后父进程成为唯一引用者仍可正常写入，最终输出 Test cow OK!
+*/
+
+const PAGES: usize = 8;
+const PAGE_SIZE: usize = 4096;
+static mut DATA: [u8; PAGES * PAGE_SIZE] = [0; PAGES * PAGE_SIZE];
+
+fn fill(value: u8) {
+    unsafe {
+        for page in 0..PAGES {
+            DATA[page * PAGE_SIZE] = value;
+            DATA[page * PAGE_SIZE + PAGE_SIZE - 1] = value;
+        }
+    }
+}
+
+fn check(value: u8) -> bool {
+    unsafe {
+        (0..PAGES).all(|page| {
+            DATA[page * PAGE_SIZE] == value && DATA[page * PAGE_SIZE + PAGE_SIZE - 1] == value
+        })
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    fill(1);
+    let pid = fork();
+    if pid == 0 {
+        // child sees the data before fork, then gets its own copy
+        assert!(check(1));
+        fill(2);
+        assert!(check(2));
+        println!("child wrote its own copy");
+        return 0;
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // the child did not touch our pages
+    assert!(check(1));
+    fill(3);
+    assert!(check(3));
+    println!("Test cow OK!");
+    0
+}
@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, fork, waitpid};
+
+/*
+理想结果：exec 不存在的程序返回 -1，子进程成功 exec 为 ch4_mmap0 并输出 Test 04_1 OK!，最终输出 Test exec OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(exec("ch5_no_such_app\0"), -1);
+    let pid = fork();
+    if pid == 0 {
+        exec("ch4_mmap0\0");
+        panic!("unreachable after exec!");
+    }
+    assert!(pid > 0);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("Test exec OK!");
+    0
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, getpid, wait};
+
+/*
+理想结果：子进程中 fork 返回 0，父进程回收全部子进程并取得各自的退出码，最终输出 Test fork OK!
+*/
+
+const CHILD_NUM: usize = 4;
+
+#[no_mangle]
+fn main() -> i32 {
+    let parent_pid = getpid();
+    let data: usize = 0x5a5a;
+    for i in 0..CHILD_NUM {
+        let pid = fork();
+        if pid == 0 {
+            let pid = getpid();
+            assert_ne!(pid, parent_pid);
+            assert_eq!(data, 0x5a5a);
+            println!("I am child {}, pid = {}", i, pid);
+            return 100 + i as i32;
+        }
+        assert!(pid > 0);
+        println!("forked child {} with pid = {}", i, pid);
+    }
+    let mut exit_code_sum: i32 = 0;
+    for _ in 0..CHILD_NUM {
+        let mut exit_code: i32 = 0;
+        assert!(wait(&mut exit_code) > 0);
+        exit_code_sum += exit_code;
+    }
+    assert_eq!(exit_code_sum, (100..100 + CHILD_NUM as i32).sum());
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), -1);
+    println!("Test fork OK!");
+    0
+}
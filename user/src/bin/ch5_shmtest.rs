@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, mprotect, shmat, shmctl, shmdt, shmget, wait, ShmFlags, IPC_PRIVATE, IPC_RMID};
+
+/*
+理想结果：父子进程通过共享内存段看到彼此的写入，按 key 可以找到同一个段，
+不能挂载到跳板页等用户不可达的地址，
+标记删除后已挂载的段仍可使用，但不能再被找到或挂载，只读挂载不能通过 mprotect 变为可写，
+其写入导致进程被杀死（退出码 -2），
+最终输出 Test shm OK!
+*/
+
+const PAGE_SIZE: usize = 4096;
+const KEY: usize = 0x5a5a;
+
+#[no_mangle]
+fn main() -> i32 {
+    let id = shmget(KEY, 2 * PAGE_SIZE, ShmFlags::IPC_CREAT | ShmFlags::IPC_EXCL);
+    assert!(id >= 0);
+    let id = id as usize;
+    assert_eq!(shmget(KEY, PAGE_SIZE, ShmFlags::empty()), id as isize);
+    assert_eq!(shmget(KEY, PAGE_SIZE, ShmFlags::IPC_CREAT | ShmFlags::IPC_EXCL), -1);
+    assert_eq!(shmget(KEY, 3 * PAGE_SIZE, ShmFlags::empty()), -1);
+    // the trampoline and addresses wrapping around are out of reach
+    assert_eq!(shmat(id, usize::MAX - PAGE_SIZE + 1, ShmFlags::empty()), -1);
+    assert_eq!(shmat(id, usize::MAX - PAGE_SIZE * 2 + 1, ShmFlags::empty()), -1);
+    let addr = shmat(id, 0, ShmFlags::empty());
+    assert!(addr > 0);
+    let addr = addr as usize;
+    let words = unsafe { core::slice::from_raw_parts_mut(addr as *mut usize, 2 * PAGE_SIZE / 8) };
+    words[0] = 1;
+    let pid = fork();
+    if pid == 0 {
+        // the child finds the segment by key and attaches it again
+        let id = shmget(KEY, 0, ShmFlags::empty()) as usize;
+        let other = shmat(id, 0, ShmFlags::empty()) as usize;
+        assert_ne!(other, addr);
+        let other_words = unsafe { core::slice::from_raw_parts_mut(other as *mut usize, 2 * PAGE_SIZE / 8) };
+        assert_eq!(other_words[0], 1);
+        other_words[PAGE_SIZE / 8] = 2;
+        assert_eq!(words[PAGE_SIZE / 8], 2);
+        // inherited from the parent
+        words[1] = 3;
+        assert_eq!(shmdt(other), 0);
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(words[PAGE_SIZE / 8], 2);
+    assert_eq!(words[1], 3);
+    // writing a read-only attachment is fatal
+    let pid = fork();
+    if pid == 0 {
+        let readonly = shmat(id, 0, ShmFlags::SHM_RDONLY) as usize;
+        assert_eq!(unsafe { *(readonly as *const usize) }, 1);
+        // mprotect can not lift it
+        assert_eq!(mprotect(readonly, PAGE_SIZE, 3), -1);
+        unsafe {
+            *(readonly as *mut usize) = 4;
+        }
+        println!("Should cause error, Test shm fail!");
+        return 0;
+    }
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    // still usable after removal, but gone for everyone else
+    assert_eq!(shmctl(id, IPC_RMID), 0);
+    words[2] = 5;
+    assert_eq!(words[2], 5);
+    assert_eq!(shmget(KEY, 0, ShmFlags::empty()), -1);
+    assert_eq!(shmat(id, 0, ShmFlags::empty()), -1);
+    assert_eq!(shmctl(id, IPC_RMID), -1);
+    assert_eq!(shmdt(addr + PAGE_SIZE), -1);
+    assert_eq!(shmdt(addr), 0);
+    // private segments are never found by key
+    let private = shmget(IPC_PRIVATE, PAGE_SIZE, ShmFlags::empty());
+    assert!(private >= 0 && private as usize != id);
+    assert_eq!(shmctl(private as usize, IPC_RMID), 0);
+    println!("Test shm OK!");
+    0
+}
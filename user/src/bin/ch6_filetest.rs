@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, write, OpenFlags};
+
+/*
+理想结果：写入文件的内容能被完整读回，截断后文件为空，对未打开或已关闭的 fd 读写返回 -1，最终输出 Test file OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let test_str = "Hello, world!";
+    let filea = "filea\0";
+    // create and write
+    let fd = open(filea, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, test_str.as_bytes()), test_str.len() as isize);
+    // write only
+    let mut buffer = [0u8; 100];
+    assert_eq!(read(fd, &mut buffer), -1);
+    close(fd);
+    // read back
+    let fd = open(filea, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let read_len = read(fd, &mut buffer) as usize;
+    assert_eq!(core::str::from_utf8(&buffer[..read_len]).unwrap(), test_str);
+    // read only
+    assert_eq!(write(fd, test_str.as_bytes()), -1);
+    assert_eq!(close(fd), 0);
+    // closed fd is gone
+    assert_eq!(read(fd, &mut buffer), -1);
+    assert_eq!(close(fd), -1);
+    assert_eq!(write(100, test_str.as_bytes()), -1);
+    // truncate
+    let fd = open(filea, OpenFlags::RDWR | OpenFlags::TRUNC);
+    assert!(fd > 0);
+    assert_eq!(read(fd as usize, &mut buffer), 0);
+    close(fd as usize);
+    // not found without CREATE
+    assert_eq!(open("fileb\0", OpenFlags::RDONLY), -1);
+    println!("Test file OK!");
+    0
+}
@@ -0,0 +1,114 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+#[macro_use]
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{close, fork, mmap, mmap_file, mprotect, msync, munmap, open, read, wait, write, MapFlags, OpenFlags};
+
+/*
+理想结果：共享文件映射的修改在 msync/munmap 后写回文件，并对 fork 出的子进程可见，
+私有文件映射的修改不会写回文件，只读文件的共享映射不能通过 mprotect 变为可写，
+共享匿名映射的所有页对 fork 出的子进程可见，start 为 0 时由内核选择映射地址，
+最终输出 Test mmap file OK!
+*/
+
+const PAGE_SIZE: usize = 4096;
+const FILE_NAME: &str = "mmapfile\0";
+
+fn read_back() -> Vec<u8> {
+    let fd = open(FILE_NAME, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buffer = vec![0u8; 2 * PAGE_SIZE];
+    assert_eq!(read(fd as usize, &mut buffer), (2 * PAGE_SIZE) as isize);
+    close(fd as usize);
+    buffer
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // two pages of 'a' and 'b'
+    let fd = open(FILE_NAME, OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::TRUNC);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut content = vec![b'a'; PAGE_SIZE];
+    content.extend_from_slice(&[b'b'; PAGE_SIZE]);
+    assert_eq!(write(fd, &content), (2 * PAGE_SIZE) as isize);
+    // the kernel chooses the address
+    let shared = mmap_file(0, 2 * PAGE_SIZE, 3, MapFlags::SHARED, fd, 0);
+    assert!(shared > 0 && shared as usize % PAGE_SIZE == 0);
+    let shared = unsafe { core::slice::from_raw_parts_mut(shared as *mut u8, 2 * PAGE_SIZE) };
+    assert_eq!(shared[0], b'a');
+    assert_eq!(shared[PAGE_SIZE], b'b');
+    shared[0] = b'x';
+    shared[PAGE_SIZE + 100] = b'y';
+    // a private mapping of the second page sees the shared changes until it writes
+    let private = mmap_file(0, PAGE_SIZE, 3, MapFlags::PRIVATE, fd, PAGE_SIZE);
+    assert!(private > 0);
+    let private = unsafe { core::slice::from_raw_parts_mut(private as *mut u8, PAGE_SIZE) };
+    assert_eq!(private[100], b'y');
+    private[0] = b'z';
+    assert_eq!(shared[PAGE_SIZE], b'b');
+    assert_eq!(msync(shared.as_ptr() as usize, 2 * PAGE_SIZE), 0);
+    let buffer = read_back();
+    assert_eq!(buffer[0], b'x');
+    assert_eq!(buffer[1], b'a');
+    assert_eq!(buffer[PAGE_SIZE], b'b');
+    assert_eq!(buffer[PAGE_SIZE + 100], b'y');
+    // the child writes through the same pages
+    let pid = fork();
+    if pid == 0 {
+        shared[1] = b'c';
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(shared[1], b'c');
+    assert_eq!(munmap(private.as_ptr() as usize, PAGE_SIZE), PAGE_SIZE as isize);
+    assert_eq!(munmap(shared.as_ptr() as usize, 2 * PAGE_SIZE), (2 * PAGE_SIZE) as isize);
+    let buffer = read_back();
+    assert_eq!(buffer[1], b'c');
+    assert_eq!(buffer[PAGE_SIZE], b'b');
+    close(fd);
+    // a read-only fd can not back a writable shared mapping, but a private one
+    let fd = open(FILE_NAME, OpenFlags::RDONLY) as usize;
+    assert_eq!(mmap_file(0, PAGE_SIZE, 3, MapFlags::SHARED, fd, 0), -1);
+    assert_eq!(mmap_file(0, PAGE_SIZE, 3, MapFlags::SHARED | MapFlags::PRIVATE, fd, 0), -1);
+    assert_eq!(mmap_file(0, PAGE_SIZE, 3, MapFlags::PRIVATE, fd, 100), -1);
+    assert!(mmap_file(0, PAGE_SIZE, 3, MapFlags::PRIVATE, fd, 0) > 0);
+    // nor can mprotect make a read-only shared mapping of it writable
+    let readonly = mmap_file(0, PAGE_SIZE, 1, MapFlags::SHARED, fd, 0);
+    assert!(readonly > 0);
+    assert_eq!(mprotect(readonly as usize, PAGE_SIZE, 3), -1);
+    assert_eq!(mprotect(readonly as usize, PAGE_SIZE, 1), PAGE_SIZE as isize);
+    close(fd);
+    // anonymous memory anywhere
+    let anonymous = mmap(0, PAGE_SIZE, 3);
+    assert!(anonymous > 0);
+    unsafe {
+        *(anonymous as *mut usize) = 42;
+        assert_eq!(*(anonymous as *const usize), 42);
+    }
+    // shared anonymous memory is shared with the child, even the pages untouched before fork
+    let anonymous = mmap_file(0, 2 * PAGE_SIZE, 3, MapFlags::SHARED | MapFlags::ANONYMOUS, 0, 0);
+    assert!(anonymous > 0);
+    let anonymous = unsafe { core::slice::from_raw_parts_mut(anonymous as *mut u8, 2 * PAGE_SIZE) };
+    anonymous[0] = b'p';
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(anonymous[0], b'p');
+        anonymous[1] = b'c';
+        anonymous[PAGE_SIZE] = b'c';
+        return 0;
+    }
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(anonymous[1], b'c');
+    assert_eq!(anonymous[PAGE_SIZE], b'c');
+    println!("Test mmap file OK!");
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{read, STDIN};
+
+/*
+配合 ch6_pipe_producer 使用，例如在 shell 中执行 ch6_pipe_producer | ch6_pipe_consumer
+理想结果：从标准输入读到 EOF，内容与 ch6_pipe_producer 的输出一致，输出 Pipeline consumer OK!
+*/
+
+const MESSAGE: &str = "Hello from the other side of the pipe!\n";
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut buffer = [0u8; 128];
+    let mut len = 0usize;
+    loop {
+        // stdin may be the console which only accepts 1 byte at a time
+        let len_read = read(STDIN, &mut buffer[len..len + 1]);
+        if len_read <= 0 {
+            break;
+        }
+        len += 1;
+        if len == buffer.len() {
+            break;
+        }
+    }
+    if &buffer[..len] != MESSAGE.as_bytes() {
+        println!("Pipeline consumer got unexpected input!");
+        return -1;
+    }
+    println!("Pipeline consumer OK!");
+    0
+}
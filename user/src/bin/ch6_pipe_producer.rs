@@ -0,0 +1,18 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+/*
+配合 ch6_pipe_consumer 使用，例如在 shell 中执行 ch6_pipe_producer | ch6_pipe_consumer
+理想结果：向标准输出写出固定的一行内容
+*/
+
+const MESSAGE: &str = "Hello from the other side of the pipe!";
+
+#[no_mangle]
+fn main() -> i32 {
+    println!("{}", MESSAGE);
+    0
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, dup2, exec, fork, pipe, waitpid, write, STDIN, STDOUT};
+
+/*
+理想结果：dup 得到最小的空闲 fd 且与原 fd 指向同一文件，对无效 fd 的 dup/dup2 返回 -1；
+ch6_pipe_producer 的输出经管道送入 ch6_pipe_consumer，两者均以 0 退出，最终输出 Test pipeline OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    // dup shares the file with the original fd
+    let fd = dup(STDOUT);
+    assert!(fd > 2);
+    let msg = "written through a dup of stdout\n";
+    assert_eq!(write(fd as usize, msg.as_bytes()), msg.len() as isize);
+    assert_eq!(close(fd as usize), 0);
+    assert_eq!(dup(fd as usize), -1);
+    assert_eq!(dup2(fd as usize, 10), -1);
+    assert_eq!(dup2(STDOUT, 10), 10);
+    assert_eq!(write(10, msg.as_bytes()), msg.len() as isize);
+    assert_eq!(close(10), 0);
+    // ch6_pipe_producer | ch6_pipe_consumer
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let producer = fork();
+    if producer == 0 {
+        dup2(pipe_fd[1], STDOUT);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        exec("ch6_pipe_producer\0");
+        unreachable!();
+    }
+    let consumer = fork();
+    if consumer == 0 {
+        dup2(pipe_fd[0], STDIN);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        exec("ch6_pipe_consumer\0");
+        unreachable!();
+    }
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(producer as usize, &mut exit_code), producer);
+    assert_eq!(exit_code, 0);
+    assert_eq!(waitpid(consumer as usize, &mut exit_code), consumer);
+    assert_eq!(exit_code, 0);
+    println!("Test pipeline OK!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fork, pipe, read, wait, write};
+
+/*
+理想结果：子进程通过管道完整收到父进程写入的长字符串（超过管道缓冲区大小），写端全部关闭后读到 EOF，最终输出 Test pipe OK!
+*/
+
+const STR: &str = "Hello, world! This line is longer than the ring buffer of a pipe.";
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    // the ends cannot be used the other way round
+    assert_eq!(write(pipe_fd[0], STR.as_bytes()), -1);
+    let mut buffer = [0u8; 128];
+    assert_eq!(read(pipe_fd[1], &mut buffer), -1);
+    if fork() == 0 {
+        // child process, read from parent
+        close(pipe_fd[1]);
+        let mut len_read = 0usize;
+        loop {
+            let len = read(pipe_fd[0], &mut buffer[len_read..]);
+            assert!(len >= 0);
+            if len == 0 {
+                // EOF
+                break;
+            }
+            len_read += len as usize;
+        }
+        close(pipe_fd[0]);
+        assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
+        println!("Read OK, child process exited!");
+        0
+    } else {
+        // parent process, write to child
+        close(pipe_fd[0]);
+        assert_eq!(write(pipe_fd[1], STR.as_bytes()), STR.len() as isize);
+        close(pipe_fd[1]);
+        let mut child_exit_code: i32 = 0;
+        wait(&mut child_exit_code);
+        assert_eq!(child_exit_code, 0);
+        println!("Test pipe OK!");
+        0
+    }
+}
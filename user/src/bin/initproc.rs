@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, fork, wait};
+
+#[no_mangle]
+fn main() -> i32 {
+    if fork() == 0 {
+        exec("user_shell\0");
+        panic!("cannot start user_shell!");
+    }
+    loop {
+        let mut exit_code: i32 = 0;
+        let pid = wait(&mut exit_code);
+        if pid == -1 {
+            // the shell has quit and no orphan is left
+            break;
+        }
+        println!(
+            "[initproc] Released a zombie process, pid={}, exit_code={}",
+            pid, exit_code,
+        );
+    }
+    0
+}
@@ -0,0 +1,259 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup2, exec, fork, getchar, open, pipe, waitpid, OpenFlags, STDIN, STDOUT};
+
+const LF: u8 = 0x0au8;
+const CR: u8 = 0x0du8;
+const DL: u8 = 0x7fu8;
+const BS: u8 = 0x08u8;
+
+const LINE_CAPACITY: usize = 128;
+/// Every word or operator takes at least one byte.
+const MAX_TOKENS: usize = LINE_CAPACITY;
+const MAX_PIPELINE: usize = 8;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Token {
+    /// [start, end) of a word in the line.
+    Word(usize, usize),
+    Pipe,
+    Input,
+    Output,
+}
+
+/// Words of a command, each one ends with '\0' in the line once parsed.
+#[derive(Copy, Clone)]
+struct Command {
+    app: Option<(usize, usize)>,
+    input: Option<(usize, usize)>,
+    output: Option<(usize, usize)>,
+}
+
+impl Command {
+    const fn empty() -> Self {
+        Self {
+            app: None,
+            input: None,
+            output: None,
+        }
+    }
+}
+
+/// Split `line` into words and the operators `|`, `<` and `>`.
+fn tokenize(line: &[u8], tokens: &mut [Token; MAX_TOKENS]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let token = match line[i] {
+            b' ' => {
+                i += 1;
+                continue;
+            }
+            b'|' => Token::Pipe,
+            b'<' => Token::Input,
+            b'>' => Token::Output,
+            _ => {
+                let start = i;
+                while i < line.len() && !b" |<>".contains(&line[i]) {
+                    i += 1;
+                }
+                tokens[count] = Token::Word(start, i);
+                count += 1;
+                continue;
+            }
+        };
+        tokens[count] = token;
+        count += 1;
+        i += 1;
+    }
+    count
+}
+
+/// Group tokens into a pipeline, return the number of commands or None if the line is malformed.
+fn parse(tokens: &[Token], commands: &mut [Command; MAX_PIPELINE]) -> Option<usize> {
+    let mut count = 1;
+    commands[0] = Command::empty();
+    let mut iter = tokens.iter();
+    while let Some(token) = iter.next() {
+        let command = &mut commands[count - 1];
+        match *token {
+            Token::Word(start, end) => {
+                if command.app.is_some() {
+                    // there are no arguments to pass to exec
+                    return None;
+                }
+                command.app = Some((start, end));
+            }
+            Token::Input | Token::Output => {
+                let file = match iter.next() {
+                    Some(Token::Word(start, end)) => (*start, *end),
+                    _ => return None,
+                };
+                let slot = if *token == Token::Input {
+                    &mut command.input
+                } else {
+                    &mut command.output
+                };
+                if slot.is_some() {
+                    return None;
+                }
+                *slot = Some(file);
+            }
+            Token::Pipe => {
+                if command.app.is_none() || count == MAX_PIPELINE {
+                    return None;
+                }
+                commands[count] = Command::empty();
+                count += 1;
+            }
+        }
+    }
+    for (i, command) in commands[..count].iter().enumerate() {
+        // only the ends of a pipeline may be redirected
+        if command.app.is_none()
+            || (i > 0 && command.input.is_some())
+            || (i < count - 1 && command.output.is_some())
+        {
+            return None;
+        }
+    }
+    Some(count)
+}
+
+/// A word as a '\0' terminated str, the byte after it must have been set to 0.
+fn word(line: &[u8], (start, end): (usize, usize)) -> &str {
+    core::str::from_utf8(&line[start..=end]).unwrap()
+}
+
+fn close_pipes(pipes: &[[usize; 2]]) {
+    for pipe_fd in pipes.iter() {
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+    }
+}
+
+/// Run in a forked child: set up its stdin/stdout and exec the app.
+fn run_command(line: &[u8], command: &Command, pipes: &[[usize; 2]], index: usize) -> i32 {
+    if let Some(input) = command.input {
+        let input = word(line, input);
+        let fd = open(input, OpenFlags::RDONLY);
+        if fd == -1 {
+            println!("Error when opening file {}", input.trim_end_matches('\0'));
+            return -4;
+        }
+        dup2(fd as usize, STDIN);
+        close(fd as usize);
+    }
+    if let Some(output) = command.output {
+        let output = word(line, output);
+        let fd = open(output, OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC);
+        if fd == -1 {
+            println!("Error when opening file {}", output.trim_end_matches('\0'));
+            return -4;
+        }
+        dup2(fd as usize, STDOUT);
+        close(fd as usize);
+    }
+    if index > 0 {
+        dup2(pipes[index - 1][0], STDIN);
+    }
+    if index < pipes.len() {
+        dup2(pipes[index][1], STDOUT);
+    }
+    close_pipes(pipes);
+    if exec(word(line, command.app.unwrap())) == -1 {
+        println!("Error when executing!");
+        return -4;
+    }
+    unreachable!();
+}
+
+fn run_line(line: &mut [u8; LINE_CAPACITY + 1], len: usize) {
+    let mut tokens = [Token::Pipe; MAX_TOKENS];
+    let token_count = tokenize(&line[..len], &mut tokens);
+    if token_count == 0 {
+        return;
+    }
+    let mut commands = [Command::empty(); MAX_PIPELINE];
+    let command_count = match parse(&tokens[..token_count], &mut commands) {
+        Some(count) => count,
+        None => {
+            println!("Invalid command!");
+            return;
+        }
+    };
+    // terminate every word, the separators after them are not needed any more
+    for token in tokens[..token_count].iter() {
+        if let Token::Word(_, end) = *token {
+            line[end] = 0;
+        }
+    }
+    let mut pipes = [[0usize; 2]; MAX_PIPELINE - 1];
+    let pipes = &mut pipes[..command_count - 1];
+    for pipe_fd in pipes.iter_mut() {
+        pipe(pipe_fd);
+    }
+    let mut pids = [0isize; MAX_PIPELINE];
+    for (i, command) in commands[..command_count].iter().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            // child process
+            user_lib::exit(run_command(line, command, pipes, i));
+        }
+        pids[i] = pid;
+    }
+    // readers see EOF only after the shell drops its copies of the write ends
+    close_pipes(pipes);
+    for pid in pids[..command_count].iter() {
+        let mut exit_code: i32 = 0;
+        let exit_pid = waitpid(*pid as usize, &mut exit_code);
+        assert_eq!(*pid, exit_pid);
+        println!("Shell: Process {} exited with code {}", pid, exit_code);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    println!("Rust user shell");
+    // one more byte for the trailing '\0' required by exec
+    let mut line = [0u8; LINE_CAPACITY + 1];
+    let mut len: usize = 0;
+    print!(">> ");
+    loop {
+        let c = getchar();
+        match c {
+            LF | CR => {
+                println!("");
+                if len > 0 {
+                    if &line[..len] == b"exit" {
+                        return 0;
+                    }
+                    run_line(&mut line, len);
+                    len = 0;
+                }
+                print!(">> ");
+            }
+            BS | DL => {
+                if len > 0 {
+                    print!("{}", BS as char);
+                    print!(" ");
+                    print!("{}", BS as char);
+                    len -= 1;
+                }
+            }
+            // only printable ascii goes into the line, so it is always valid utf-8
+            0x20..=0x7e => {
+                if len < LINE_CAPACITY {
+                    print!("{}", c as char);
+                    line[len] = c;
+                    len += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
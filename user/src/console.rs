@@ -1,6 +1,7 @@
+pub const STDIN: usize = 0;
 pub const STDOUT: usize = 1;
 
-use super::write;
+use super::{read, write};
 use core::fmt::{self, Write};
 
 struct Stdout;
@@ -16,6 +17,12 @@ pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+pub fn getchar() -> u8 {
+    let mut c = [0u8; 1];
+    read(STDIN, &mut c);
+    c[0]
+}
+
 #[macro_export]
 macro_rules! print {
     ($fmt: literal $(, $($arg: tt)+)?) => {
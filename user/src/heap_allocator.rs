@@ -0,0 +1,30 @@
+use crate::sbrk;
+use buddy_system_allocator::{Heap, LockedHeapWithRescue};
+use core::alloc::Layout;
+
+const HEAP_ORDER: usize = 32;
+const PAGE_SIZE: usize = 4096;
+/// Grow the heap by at least this much at a time.
+const HEAP_GROW_SIZE: usize = PAGE_SIZE * 16;
+
+#[global_allocator]
+static HEAP_ALLOCATOR: LockedHeapWithRescue<HEAP_ORDER> = LockedHeapWithRescue::new(grow_heap);
+
+/// Called when the heap runs out, take more memory from the kernel through sbrk.
+fn grow_heap(heap: &mut Heap<HEAP_ORDER>, layout: &Layout) {
+    // twice the block size, so that an aligned block fits whatever the break is
+    let size = (layout.size().max(layout.align()).next_power_of_two() * 2).max(HEAP_GROW_SIZE);
+    let size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+    let start = sbrk(size as isize);
+    if start == -1 {
+        return;
+    }
+    unsafe {
+        heap.add_to_heap(start as usize, start as usize + size);
+    }
+}
+
+#[alloc_error_handler]
+pub fn handle_alloc_error(layout: Layout) -> ! {
+    panic!("Heap allocation error, layout = {:?}", layout);
+}
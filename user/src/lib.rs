@@ -2,15 +2,52 @@
 #![feature(asm)]
 #![feature(linkage)]
 #![feature(panic_info_message)]
+#![feature(alloc_error_handler)]
+
+#[macro_use]
+extern crate bitflags;
+extern crate alloc;
 
 #[macro_use]
 pub mod console;
+mod heap_allocator;
 mod lang_items;
 mod syscall;
 
-pub use console::STDOUT;
+pub use console::{getchar, STDIN, STDOUT};
 use syscall::*;
 
+bitflags! {
+    pub struct OpenFlags: u32 {
+        const RDONLY = 0;
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREATE = 1 << 9;
+        const TRUNC = 1 << 10;
+    }
+}
+
+/// `path` must end with '\0'.
+pub fn open(path: &str, flags: OpenFlags) -> isize {
+    sys_open(path, flags.bits)
+}
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+pub fn dup2(old_fd: usize, new_fd: usize) -> isize {
+    sys_dup2(old_fd, new_fd)
+}
+/// Fill `pipe_fd` with the read end and the write end.
+pub fn pipe(pipe_fd: &mut [usize]) -> isize {
+    sys_pipe(pipe_fd)
+}
+
+pub fn read(fd: usize, buf: &mut [u8]) -> isize {
+    sys_read(fd, buf)
+}
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
@@ -34,14 +71,117 @@ pub fn sleep(milliseconds: usize) -> isize {
     sys_sleep(milliseconds)
 }
 
+bitflags! {
+    pub struct MapFlags: u32 {
+        const SHARED = 1 << 0;
+        const PRIVATE = 1 << 1;
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
+/// Map zero-filled private memory, return the length or the address chosen if `start` is 0.
 pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
-    sys_mmap(start, len, prot)
+    let flags = MapFlags::PRIVATE | MapFlags::ANONYMOUS;
+    sys_mmap(start, len, prot, flags.bits as usize, 0, 0)
+}
+/// Map the file `fd` from `offset`, `offset` must be page aligned.
+pub fn mmap_file(start: usize, len: usize, prot: usize, flags: MapFlags, fd: usize, offset: usize) -> isize {
+    sys_mmap(start, len, prot, flags.bits as usize, fd, offset)
+}
+/// Write the changes of shared file mappings back to the files.
+pub fn msync(start: usize, len: usize) -> isize {
+    sys_msync(start, len)
+}
+
+/// Key of a segment which is never found by other tasks.
+pub const IPC_PRIVATE: usize = 0;
+pub const IPC_RMID: usize = 0;
+
+bitflags! {
+    pub struct ShmFlags: u32 {
+        const IPC_CREAT = 0o1000;
+        const IPC_EXCL = 0o2000;
+        const SHM_RDONLY = 0o10000;
+    }
+}
+
+/// Return the id of the shared memory segment of `key`.
+pub fn shmget(key: usize, size: usize, flags: ShmFlags) -> isize {
+    sys_shmget(key, size, flags.bits)
+}
+/// Attach a segment at `start`, or where the kernel chooses if it is 0, return the address.
+pub fn shmat(id: usize, start: usize, flags: ShmFlags) -> isize {
+    sys_shmat(id, start, flags.bits)
+}
+pub fn shmdt(start: usize) -> isize {
+    sys_shmdt(start)
+}
+/// Only IPC_RMID, which marks a segment for removal, it is freed once nobody attaches it.
+pub fn shmctl(id: usize, cmd: usize) -> isize {
+    sys_shmctl(id, cmd)
+}
+
+/// Move the program break by `size` bytes, return the old break or -1.
+pub fn sbrk(size: isize) -> isize {
+    sys_sbrk(size)
+}
+
+/// Set the program break to `addr`, return 0 or -1.
+pub fn brk(addr: usize) -> isize {
+    let current = sys_sbrk(0);
+    if sys_sbrk(addr as isize - current) == -1 {
+        -1
+    } else {
+        0
+    }
 }
 
 pub fn munmap(start: usize, len: usize) -> isize {
     sys_munmap(start, len)
 }
 
+pub fn mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_mprotect(start, len, prot)
+}
+
+pub fn getpid() -> isize {
+    sys_getpid()
+}
+
+pub fn fork() -> isize {
+    sys_fork()
+}
+
+/// `path` must end with '\0'.
+pub fn exec(path: &str) -> isize {
+    sys_exec(path)
+}
+
+/// Wait for any child to exit, return -1 if there is no child at all.
+pub fn wait(exit_code: &mut i32) -> isize {
+    loop {
+        match sys_waitpid(-1, exit_code as *mut _) {
+            -2 => {
+                yield_();
+            }
+            // -1 or a real pid
+            exit_pid => return exit_pid,
+        }
+    }
+}
+
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    loop {
+        match sys_waitpid(pid as isize, exit_code as *mut _) {
+            -2 => {
+                yield_();
+            }
+            // -1 or a real pid
+            exit_pid => return exit_pid,
+        }
+    }
+}
+
 fn clear_bss() {
     extern "C" {
         fn start_bss();